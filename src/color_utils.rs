@@ -1,34 +1,378 @@
-use palette::{Srgb, Hsl, FromColor};
+//! Colour operations for the themes
+//!
+//! Lightness and saturation are changed in OKLCH and colours are mixed in
+//! OKLab, where equal steps look equal whatever the hue. All of them keep the
+//! alpha of their input.
+
+use palette::{Srgb, Oklab, Oklch, Clamp, FromColor, color_difference::Wcag21RelativeContrast};
+use palette::convert::FromColorUnclamped;
 use iced_core::Color;
 
-fn to_hsl(color: Color) -> Hsl {
-    Hsl::from_color(Srgb::from(color))
+/// Raises the OKLCH lightness by `amount`, from 0 for black to 1 for white
+pub fn lighten(color: Color, amount: f32) -> Color {
+    let lch = to_oklch(color);
+
+    from_oklch(Oklch { l: (lch.l + amount).min(1.0), ..lch }, color.a)
 }
 
-fn from_hsl(hsl: Hsl) -> Color {
-    Srgb::from_color(hsl).into()
+/// Takes `amount` of the chroma away, 1 leaves a grey of the same lightness
+pub fn desaturate(color: Color, amount: f32) -> Color {
+    let lch = to_oklch(color);
+
+    from_oklch(Oklch { chroma: lch.chroma * (1.0 - amount.clamp(0.0, 1.0)), ..lch }, color.a)
 }
 
-pub fn lighten(color: Color, amount: f32) -> Color {
-    let mut hsl = to_hsl(color);
+/// `from` at `amount` 0, `to` at 1, through OKLab
+pub fn mix(from: Color, to: Color, amount: f32) -> Color {
+    let (a, b) = (Oklab::from_color(Srgb::from(from)), Oklab::from_color(Srgb::from(to)));
+    let lerp = |a: f32, b: f32| a + (b - a) * amount;
 
-    hsl.lightness = if hsl.lightness + amount > 1.0 {
-        1.0
-    } else {
-        hsl.lightness + amount
+    let srgb = Srgb::from_color(Oklab::new(lerp(a.l, b.l), lerp(a.a, b.a), lerp(a.b, b.b)));
+
+    Color::from_rgba(srgb.red, srgb.green, srgb.blue, lerp(from.a, to.a))
+}
+
+/// `top` drawn over `bottom`, the source-over operator of a browser on
+/// gamma-encoded sRGB
+pub fn over(top: Color, bottom: Color) -> Color {
+    let alpha = top.a + bottom.a * (1.0 - top.a);
+
+    if alpha == 0.0 {
+        return Color::TRANSPARENT;
+    }
+
+    let channel = |top_channel: f32, bottom_channel: f32| {
+        (top_channel * top.a + bottom_channel * bottom.a * (1.0 - top.a)) / alpha
     };
 
-    from_hsl(hsl)
+    Color::from_rgba(
+        channel(top.r, bottom.r),
+        channel(top.g, bottom.g),
+        channel(top.b, bottom.b),
+        alpha,
+    )
 }
 
-pub fn darken(color: Color, amount: f32) -> Color {
-    let mut hsl = to_hsl(color);
+/// WCAG 2.1 contrast ratio, from 1 for the same luminance to 21 for black on
+/// white, regardless of alpha
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    Srgb::from(a).relative_contrast(Srgb::from(b))
+}
+
+/// APCA lightness contrast (Lc) of `text` on `background`, as of version
+/// 0.0.98G-4g
+///
+/// Around 106 for black on white, -108 for white on black: unlike
+/// [`contrast_ratio`] it tells dark text from light text, and it weighs dark
+/// backgrounds more realistically. Body text wants at least 75 either way.
+pub fn apca_contrast(text: Color, background: Color) -> f32 {
+    // screen luminance with a plain 2.4 gamma, and a soft clamp of the blacks
+    let luminance = |color: Color| {
+        let y = 0.2126729 * color.r.powf(2.4) + 0.7151522 * color.g.powf(2.4) + 0.0721750 * color.b.powf(2.4);
+
+        if y < 0.022 { y + (0.022 - y).powf(1.414) } else { y }
+    };
+
+    let (text, background) = (luminance(text), luminance(background));
 
-    hsl.lightness = if hsl.lightness - amount < 0.0 {
-        0.0
+    if (background - text).abs() < 0.0005 {
+        return 0.0;
+    }
+
+    let contrast = if background > text {
+        // dark text on a light background
+        let sapc = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+        if sapc < 0.1 { 0.0 } else { sapc - 0.027 }
     } else {
-        hsl.lightness - amount
+        let sapc = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+        if sapc > -0.1 { 0.0 } else { sapc + 0.027 }
     };
 
-    from_hsl(hsl)
-}
\ No newline at end of file
+    contrast * 100.0
+}
+
+/// WCAG AA minimum for body text
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+
+/// OKLCH lightness between two surface levels
+const SURFACE_STEP: f32 = 0.04;
+
+/// Every colour a theme needs, derived from a background and an accent
+///
+/// Lightness steps are taken in OKLCH, so they look the same size whatever
+/// the hue. Surfaces step away from the text where there is room for it, the
+/// text keeps at least [`MIN_TEXT_CONTRAST`] against all of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TonalPalette {
+    /// The background first, then more and more inset surfaces
+    pub surfaces: [Color; 4],
+    pub border: Color,
+    pub text: Color,
+    /// Secondary text such as placeholders, still readable on the background
+    pub text_muted: Color,
+    pub accent: Color,
+    pub accent_hovered: Color,
+    pub accent_pressed: Color,
+    /// Text on the accent
+    pub on_accent: Color,
+    pub disabled: Color,
+    pub disabled_text: Color,
+    pub mark_normal: Color,
+    pub mark_bold: Color,
+}
+
+impl TonalPalette {
+    /// A translucent accent is flattened onto the background
+    pub fn generate(background: Color, accent: Color) -> Self {
+        let background = Color { a: 1.0, ..background };
+        let accent = over(accent, background);
+        let background_lch = to_oklch(background);
+        let accent_lch = to_oklch(accent);
+
+        // light text on dark backgrounds, the side with the most contrast
+        let light_text = contrast_ratio(Color::WHITE, background) >= contrast_ratio(Color::BLACK, background);
+        // towards the text
+        let towards = if light_text { 1.0 } else { -1.0 };
+
+        let shade = |lch: Oklch, amount: f32| {
+            from_oklch(Oklch { l: (lch.l + towards * amount).clamp(0.0, 1.0), ..lch }, 1.0)
+        };
+
+        // away from the text keeps the most contrast, unless the background
+        // is too close to black or white to leave room for the levels
+        let deepest = background_lch.l - towards * SURFACE_STEP * 3.0;
+        let surface_step = if (0.0..=1.0).contains(&deepest) { -SURFACE_STEP } else { SURFACE_STEP };
+
+        let surfaces = [0, 1, 2, 3].map(|level| shade(background_lch, surface_step * level as f32));
+
+        // a hint of the background hue keeps the text from looking pasted on
+        let text = Oklch {
+            l: if light_text { 0.95 } else { 0.2 },
+            chroma: background_lch.chroma.min(0.02),
+            ..background_lch
+        };
+        let text = surfaces
+            .iter()
+            .fold(from_oklch(text, 1.0), |text, &surface| with_contrast(text, surface, MIN_TEXT_CONTRAST));
+
+        let text_muted = with_contrast(
+            from_oklch(Oklch { l: (to_oklch(text).l + background_lch.l) / 2.0, ..to_oklch(text) }, 1.0),
+            background,
+            MIN_TEXT_CONTRAST,
+        );
+
+        // APCA picks white text on mid tones more often, rightly so
+        let on_accent = if apca_contrast(Color::WHITE, accent).abs() >= apca_contrast(Color::BLACK, accent).abs() {
+            with_contrast(from_oklch(Oklch { l: 0.98, chroma: 0.0, ..accent_lch }, 1.0), accent, MIN_TEXT_CONTRAST)
+        } else {
+            with_contrast(from_oklch(Oklch { l: 0.15, chroma: 0.0, ..accent_lch }, 1.0), accent, MIN_TEXT_CONTRAST)
+        };
+
+        let disabled = desaturate(shade(background_lch, 0.1), 1.0);
+
+        TonalPalette {
+            surfaces,
+            border: shade(background_lch, 0.15),
+            text,
+            text_muted,
+            accent,
+            accent_hovered: shade(accent_lch, 0.06),
+            accent_pressed: shade(accent_lch, -0.06),
+            on_accent,
+            disabled,
+            disabled_text: desaturate(shade(background_lch, 0.3), 1.0),
+            mark_normal: shade(background_lch, 0.08),
+            mark_bold: shade(background_lch, 0.25),
+        }
+    }
+}
+
+fn to_oklch(color: Color) -> Oklch {
+    Oklch::from_color(Srgb::from(color))
+}
+
+/// Out of gamut colours lose chroma until they fit in sRGB, keeping their
+/// lightness and hue
+fn from_oklch(lch: Oklch, alpha: f32) -> Color {
+    let in_gamut = |srgb: Srgb| {
+        [srgb.red, srgb.green, srgb.blue].iter().all(|channel| (-1e-4..=1.0 + 1e-4).contains(channel))
+    };
+
+    let mut srgb = Srgb::from_color_unclamped(lch);
+
+    if !in_gamut(srgb) {
+        let (mut inside, mut outside) = (0.0, lch.chroma);
+
+        for _ in 0..16 {
+            let chroma = (inside + outside) / 2.0;
+
+            if in_gamut(Srgb::from_color_unclamped(Oklch { chroma, ..lch })) {
+                inside = chroma;
+            } else {
+                outside = chroma;
+            }
+        }
+
+        srgb = Srgb::from_color_unclamped(Oklch { chroma: inside, ..lch });
+    }
+
+    let srgb = srgb.clamp();
+
+    Color::from_rgba(srgb.red, srgb.green, srgb.blue, alpha)
+}
+
+/// `color` with its OKLCH lightness moved away from `background` just enough
+/// to reach `min_contrast`, or as far as it goes
+pub fn with_contrast(color: Color, background: Color, min_contrast: f32) -> Color {
+    if contrast_ratio(color, background) >= min_contrast {
+        return color;
+    }
+
+    let lch = to_oklch(color);
+    let at = |l: f32| from_oklch(Oklch { l, ..lch }, color.a);
+
+    // the end with the most contrast, white or black
+    let end = if contrast_ratio(at(1.0), background) >= contrast_ratio(at(0.0), background) { 1.0 } else { 0.0 };
+
+    if contrast_ratio(at(end), background) < min_contrast {
+        return at(end);
+    }
+
+    // contrast grows monotonically from `lch.l` to `end`
+    let (mut short, mut enough) = (lch.l, end);
+
+    for _ in 0..24 {
+        let middle = (short + enough) / 2.0;
+
+        if contrast_ratio(at(middle), background) >= min_contrast {
+            enough = middle;
+        } else {
+            short = middle;
+        }
+    }
+
+    at(enough)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(left: Color, right: Color) {
+        let close = left
+            .into_linear()
+            .iter()
+            .zip(right.into_linear())
+            .all(|(left, right)| (left - right).abs() < 1e-3);
+
+        assert!(close, "{:?} is not {:?}", left, right);
+    }
+
+    #[test]
+    fn lightness_changes_keep_hue_and_alpha() {
+        let blue = Color::from_rgba8(0x5E, 0x7C, 0xE2, 0.5);
+
+        let lighter = lighten(blue, 0.1);
+
+        assert_eq!(lighter.a, 0.5);
+        assert!((to_oklch(lighter).l - to_oklch(blue).l - 0.1).abs() < 1e-3);
+        assert!((to_oklch(lighter).hue.into_degrees() - to_oklch(blue).hue.into_degrees()).abs() < 1.0);
+        assert_close(lighten(blue, 2.0), Color::from_rgba(1., 1., 1., 0.5));
+
+        let grey = desaturate(blue, 1.0);
+        assert!(to_oklch(grey).chroma < 1e-3);
+        assert!((to_oklch(grey).l - to_oklch(blue).l).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mixes_through_oklab() {
+        let red = Color::from_rgb(1., 0., 0.);
+        let transparent_blue = Color::from_rgba(0., 0., 1., 0.);
+
+        assert_close(mix(red, transparent_blue, 0.), red);
+        assert_close(mix(red, transparent_blue, 1.), transparent_blue);
+        assert_eq!(mix(red, transparent_blue, 0.25).a, 0.75);
+
+        // half way in lightness, not the dark purple of an sRGB average
+        let grey = mix(Color::BLACK, Color::WHITE, 0.5);
+        assert!((to_oklch(grey).l - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn composites_source_over() {
+        let white = Color::WHITE;
+        let half_black = Color::from_rgba(0., 0., 0., 0.5);
+
+        assert_close(over(half_black, white), Color::from_rgb(0.5, 0.5, 0.5));
+        assert_close(over(white, half_black), white);
+        assert_close(over(half_black, half_black), Color::from_rgba(0., 0., 0., 0.75));
+        assert_eq!(over(Color::TRANSPARENT, Color::TRANSPARENT), Color::TRANSPARENT);
+    }
+
+    #[test]
+    fn measures_contrast() {
+        assert!((contrast_ratio(Color::BLACK, Color::WHITE) - 21.).abs() < 1e-3);
+        assert_eq!(contrast_ratio(Color::WHITE, Color::WHITE), 1.);
+
+        // reference values of the APCA calculator
+        assert!((apca_contrast(Color::BLACK, Color::WHITE) - 106.04).abs() < 0.1);
+        assert!((apca_contrast(Color::WHITE, Color::BLACK) + 107.88).abs() < 0.1);
+        assert!((apca_contrast(Color::from_rgb8(0x88, 0x88, 0x88), Color::WHITE) - 63.06).abs() < 0.1);
+        assert_eq!(apca_contrast(Color::WHITE, Color::WHITE), 0.);
+    }
+
+    #[test]
+    fn text_is_readable_on_every_surface() {
+        let accent = Color::from_rgb8(0x5E, 0x7C, 0xE2);
+
+        for background in [
+            Color::BLACK,
+            Color::WHITE,
+            Color::from_rgb8(0x20, 0x22, 0x25),
+            // mid greys are the hardest, neither white nor black has much room
+            Color::from_rgb8(0x76, 0x76, 0x76),
+            Color::from_rgb8(0x80, 0x40, 0xC0),
+            Color::from_rgb8(0xF2, 0xE0, 0x40),
+        ] {
+            let tones = TonalPalette::generate(background, accent);
+
+            assert_eq!(tones.surfaces[0], from_oklch(to_oklch(background), 1.0));
+
+            for surface in tones.surfaces {
+                let contrast = contrast_ratio(tones.text, surface);
+                assert!(contrast >= MIN_TEXT_CONTRAST, "{} on {:?} for {:?}", contrast, surface, background);
+            }
+
+            assert!(contrast_ratio(tones.text_muted, background) >= MIN_TEXT_CONTRAST);
+            assert!(contrast_ratio(tones.on_accent, tones.accent) >= MIN_TEXT_CONTRAST);
+        }
+    }
+
+    #[test]
+    fn surfaces_step_away_from_the_text_when_there_is_room() {
+        let lightness = |colors: [Color; 4]| colors.map(|color| to_oklch(color).l);
+
+        let dark = TonalPalette::generate(Color::from_rgb8(0x20, 0x22, 0x25), Color::WHITE);
+        assert!(lightness(dark.surfaces).windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(to_oklch(dark.mark_bold).l > to_oklch(dark.mark_normal).l);
+
+        let grey = TonalPalette::generate(Color::from_rgb8(0xC0, 0xC0, 0xC0), Color::BLACK);
+        assert!(lightness(grey.surfaces).windows(2).all(|pair| pair[0] < pair[1]));
+
+        // no lighter than white, they darken instead
+        let white = TonalPalette::generate(Color::WHITE, Color::BLACK);
+        assert!(lightness(white.surfaces).windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn contrast_is_raised_just_enough() {
+        let background = Color::from_rgb8(0x20, 0x22, 0x25);
+        let dim = Color::from_rgb8(0x50, 0x50, 0x50);
+
+        let raised = with_contrast(dim, background, MIN_TEXT_CONTRAST);
+
+        assert!(contrast_ratio(raised, background) >= MIN_TEXT_CONTRAST);
+        assert!(contrast_ratio(raised, background) < MIN_TEXT_CONTRAST + 0.05);
+        assert_eq!(with_contrast(Color::WHITE, background, MIN_TEXT_CONTRAST), Color::WHITE);
+    }
+}
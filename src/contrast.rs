@@ -0,0 +1,227 @@
+//! Readability checks of the theme appearances
+//!
+//! Every text colour a [`Theme`] gives its widgets is paired with what it's
+//! drawn on, in every style and state, and the pairs are measured with the
+//! WCAG 2.1 contrast ratio. Translucent colours are composited onto the
+//! window background first.
+
+use std::fmt;
+
+use iced_core::Color;
+use iced_core::Background;
+use iced_widget::{button, checkbox, text, text_input};
+
+use crate::color_utils::{contrast_ratio, over};
+use crate::h_slider::style::{Appearance, StyleSheet};
+use crate::theme::{ButtonStyle, CheckboxStyle, HSliderStyleType, TextInputStyle, TextStyle, Theme};
+
+/// A pair of colours below the required contrast
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastIssue {
+    /// The widget, style, state and the parts the colours belong to
+    pub place: String,
+    pub foreground: Color,
+    pub background: Color,
+    pub ratio: f32,
+}
+
+impl fmt::Display for ContrastIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: contrast {:.2} of {} on {}",
+            self.place,
+            self.ratio,
+            hex(self.foreground),
+            hex(self.background)
+        )
+    }
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// The pairs of `theme` whose contrast is under `min_contrast`
+pub fn check(theme: &Theme, min_contrast: f32) -> Vec<ContrastIssue> {
+    pairs(theme)
+        .into_iter()
+        .filter_map(|(place, foreground, background)| {
+            let ratio = contrast_ratio(foreground, background);
+
+            (ratio < min_contrast).then_some(ContrastIssue {
+                place,
+                foreground,
+                background,
+                ratio,
+            })
+        })
+        .collect()
+}
+
+/// Opaque text colours and what they are drawn on
+fn pairs(theme: &Theme) -> Vec<(String, Color, Color)> {
+    let palette = theme.palette();
+    let window = palette.background;
+    let mut pairs = Vec::new();
+
+    for style in HSliderStyleType::ALL {
+        let states: [(&str, Appearance); 3] = [
+            ("active", theme.active(style)),
+            ("hovered", theme.hovered(style)),
+            ("focused", theme.focused(style)),
+        ];
+
+        for (state, appearance) in states {
+            let background = over(appearance.background_color, window);
+            let rail = over(appearance.rail_color, background);
+            let place = |part: &str| format!("h_slider {:?} {}: text marks on {}", style, state, part);
+
+            // the marks are drawn next to the rail, on the window
+            pairs.push((place("the window"), over(appearance.text_mark_color, window), window));
+            pairs.push((place("the rail"), over(appearance.text_mark_color, rail), rail));
+            pairs.push((place("the background"), over(appearance.text_mark_color, background), background));
+        }
+    }
+
+    for style in TextStyle::ALL {
+        let color = text::StyleSheet::appearance(theme, style).color.unwrap_or(palette.text);
+
+        pairs.push((format!("text {:?}", style), over(color, window), window));
+    }
+
+    for style in CheckboxStyle::ALL {
+        for is_checked in [false, true] {
+            let states = [
+                ("active", checkbox::StyleSheet::active(theme, &style, is_checked)),
+                ("hovered", checkbox::StyleSheet::hovered(theme, &style, is_checked)),
+            ];
+
+            for (state, appearance) in states {
+                let color = appearance.text_color.unwrap_or(palette.text);
+                let checked = if is_checked { "checked" } else { "unchecked" };
+
+                pairs.push((
+                    format!("checkbox {:?} {} {}: label", style, checked, state),
+                    over(color, window),
+                    window,
+                ));
+            }
+        }
+    }
+
+    for style in ButtonStyle::ALL {
+        let states = [
+            ("active", button::StyleSheet::active(theme, &style)),
+            ("hovered", button::StyleSheet::hovered(theme, &style)),
+            ("pressed", button::StyleSheet::pressed(theme, &style)),
+        ];
+
+        for (state, appearance) in states {
+            let background = over(fill(appearance.background), window);
+
+            pairs.push((
+                format!("button {:?} {}: label", style, state),
+                over(appearance.text_color, background),
+                background,
+            ));
+        }
+    }
+
+    for style in TextInputStyle::ALL {
+        let states = [
+            ("active", text_input::StyleSheet::active(theme, &style)),
+            ("focused", text_input::StyleSheet::focused(theme, &style)),
+        ];
+
+        for (state, appearance) in states {
+            let background = over(fill(Some(appearance.background)), window);
+            let colors = [
+                ("value", text_input::StyleSheet::value_color(theme, &style)),
+                ("placeholder", text_input::StyleSheet::placeholder_color(theme, &style)),
+            ];
+
+            for (part, color) in colors {
+                pairs.push((
+                    format!("text_input {:?} {}: {}", style, state, part),
+                    over(color, background),
+                    background,
+                ));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// The colour of a widget background, gradients aren't used by the themes
+fn fill(background: Option<Background>) -> Color {
+    match background {
+        Some(Background::Color(color)) => color,
+        _ => Color::TRANSPARENT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_utils::{TonalPalette, MIN_TEXT_CONTRAST};
+    use crate::theme::{AppearanceOverrides, CustomTheme, Palette};
+
+    /// The theme of a file with only a background and a primary colour
+    fn custom(background: Color, primary: Color) -> Theme {
+        let palette = Palette {
+            background,
+            text: TonalPalette::generate(background, primary).text,
+            primary,
+            danger: Palette::DARK.danger,
+        };
+
+        Theme::Custom(CustomTheme::new(palette, AppearanceOverrides::default()))
+    }
+
+    #[test]
+    fn every_theme_is_readable() {
+        let themes = [
+            Theme::Dark,
+            Theme::Light,
+            Theme::HighContrast,
+            custom(Color::from_rgb8(0x2A, 0x1A, 0x3A), Color::from_rgb8(0xFF, 0x70, 0xA0)),
+            custom(Color::from_rgb8(0xFA, 0xF4, 0xE8), Color::from_rgb8(0x2E, 0x7D, 0x32)),
+            Theme::Custom(crate::theme_file::load("themes/amber.toml").unwrap()),
+        ];
+
+        let issues: Vec<String> = themes
+            .iter()
+            .flat_map(|theme| {
+                check(theme, MIN_TEXT_CONTRAST)
+                    .into_iter()
+                    .map(move |issue| format!("{}: {}", theme.name(), issue))
+            })
+            .collect();
+
+        assert!(issues.is_empty(), "unreadable pairs:\n{}", issues.join("\n"));
+    }
+
+    #[test]
+    fn reports_unreadable_pairs() {
+        let grey = Color::from_rgb8(0x77, 0x77, 0x77);
+        let theme = Theme::Custom(CustomTheme::new(
+            Palette { text: grey, ..Palette::DARK },
+            AppearanceOverrides {
+                text_mark_color: Some(Color::from_rgb8(0x10, 0x10, 0x10)),
+                ..AppearanceOverrides::default()
+            },
+        ));
+
+        let issues = check(&theme, MIN_TEXT_CONTRAST);
+
+        assert!(issues.iter().any(|issue| issue.place == "h_slider Classic hovered: text marks on the rail"));
+        assert!(issues.iter().any(|issue| issue.place == "text Default" && issue.foreground == grey));
+        assert!(issues.iter().any(|issue| issue.place == "text_input Default focused: value"));
+        assert!(issues.iter().all(|issue| issue.ratio < MIN_TEXT_CONTRAST));
+        assert!(check(&theme, 1.0).is_empty());
+    }
+}
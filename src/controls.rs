@@ -1,83 +1,625 @@
-use crate::h_slider::{HSlider, normal::Normal};
-use crate::theme::Theme;
+use crate::h_slider::{FillOrigin, HSlider, Marker, normal::{Normal, NormalParam}, range::HRangeSlider, taper::TaperedRange};
+use crate::history::History;
+use crate::midi::{MidiEvent, MidiMap, MidiTarget};
+use crate::osc::{OscBroadcaster, OscCommand, OscState};
+use crate::preset::{Preset, PresetError, PresetStore};
+use crate::session::Session;
+use crate::v_slider::VSlider;
+use crate::theme::{ButtonStyle, CustomTheme, HSliderStyleType, Theme, TextInputStyle, TextStyle};
 use crate::speed::{
-    MarkWeight, SpeedMode, SpeedValue, SpeedRange,
-    DEFAULT_QUANTIZED_SPEED_INDEX, QUANTIZED_SPEEDS
+    parse_speed, ParseSpeedError, Ratio, SpeedMode, SpeedValue, SpeedRange, SpeedTable,
+    smoothing::{Ramp, SpeedSmoother}
 };
 
+use std::time::{Duration, Instant};
+
 // most generic iced renderer, used for the Program associated type
 use iced::Renderer;
 use iced::{Element, Color};
 
-use iced_widget::{Row, Text, Checkbox, Column};
-use iced_winit::core::{Alignment, Length};
+use iced_widget::{text_input, Button, Row, Text, TextInput, Checkbox, Column, MouseArea};
+use iced_winit::core::widget::{self, operation::{Focusable, Operation, Outcome}};
+use iced_winit::core::{Alignment, Length, Rectangle};
 use iced_winit::runtime::{Program, Command};
 use lazy_static::lazy_static;
 
 pub struct Controls {
-    background_color: Color,
+    theme: Theme,
+    /// The last theme read from the theme file
+    custom_theme: Option<CustomTheme>,
+    /// Why the theme file couldn't be read
+    theme_error: Option<String>,
     speed_mode: SpeedMode,
     speed_range: SpeedRange,
-    speed: SpeedValue
+    speed_table: SpeedTable,
+    speed_normals: Vec<f32>,
+    markers: Vec<Marker>,
+    speed: SpeedValue,
+    smoothed_speed: SpeedSmoother,
+    last_tick: Option<Instant>,
+    history: History<UndoState>,
+    /// The state when the current slider drag started
+    drag_start: Option<UndoState>,
+    loop_region: (Normal, Normal),
+    speed_entry: Option<SpeedEntry>,
+    last_readout_press: Option<Instant>,
+    presets: Option<PresetPanel>,
+    midi_map: MidiMap,
+    /// The target the next MIDI controller will be bound to
+    midi_learn: Option<MidiTarget>,
+    osc: Option<OscBroadcaster>
+}
+
+/// The stored presets, and the two being morphed
+struct PresetPanel {
+    store: PresetStore,
+    names: Vec<String>,
+    /// The name to save or rename a preset to
+    name: String,
+    morph_from: Option<(String, Preset)>,
+    morph_to: Option<(String, Preset)>,
+    morph_amount: Normal,
+    error: Option<String>
+}
+
+/// The text typed in the speed readout while it's being edited
+struct SpeedEntry {
+    text: String,
+    error: Option<ParseSpeedError>
+}
+
+/// Finds out whether the speed entry still has the keyboard focus
+struct SpeedEntryBlurCheck {
+    is_focused: bool
+}
+
+impl Operation<Message> for SpeedEntryBlurCheck {
+    fn container(
+        &mut self,
+        _id: Option<&widget::Id>,
+        _bounds: Rectangle,
+        operate_on_children: &mut dyn FnMut(&mut dyn Operation<Message>)
+    ) {
+        operate_on_children(self)
+    }
+
+    fn focusable(&mut self, state: &mut dyn Focusable, id: Option<&widget::Id>) {
+        if id == Some(&widget::Id::from(SPEED_ENTRY_ID.clone())) {
+            self.is_focused = state.is_focused();
+        }
+    }
+
+    fn finish(&self) -> Outcome<Message> {
+        if self.is_focused {
+            Outcome::None
+        } else {
+            Outcome::Some(Message::SpeedEntryCancelled)
+        }
+    }
 }
 
+/// What undo and redo go back and forth between
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UndoState {
+    speed: SpeedValue,
+    speed_mode: SpeedMode,
+    /// Presets move it along with the speed
+    loop_region: (Normal, Normal)
+}
+
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(300);
+const SPEED_RAMP_TIME: Duration = Duration::from_millis(150);
+
 #[derive(Debug, Clone)]
 pub enum Message {
+    SetTheme(Theme),
+    /// The theme file was read, switches to it when it could be
+    ThemeFileLoaded(Result<CustomTheme, String>),
     SetSpeed(SpeedValue),
-    SetSpeedMode(SpeedMode)
+    SetSpeedMode(SpeedMode),
+    /// A drag of a speed slider started
+    SpeedGrabbed,
+    /// A drag of a speed slider ended
+    SpeedReleased,
+    Undo,
+    Redo,
+    LoopRegionChanged(Normal, Normal),
+    ReadoutPressed,
+    SpeedEntryChanged(String),
+    SpeedEntrySubmitted,
+    /// Closes the speed entry without applying it
+    SpeedEntryCancelled,
+    /// A frame while the speed is gliding
+    Tick(Instant),
+    PresetNameChanged(String),
+    SavePreset,
+    RecallPreset(String),
+    /// Renames a preset to the typed name
+    RenamePreset(String),
+    DeletePreset(String),
+    MorphFrom(String),
+    MorphTo(String),
+    MorphChanged(Normal),
+    #[cfg_attr(not(feature = "midi"), allow(dead_code))]
+    Midi(MidiEvent),
+    /// Binds the next MIDI controller to a target, or stops waiting for one
+    MidiLearn(MidiTarget),
+    Osc(OscCommand)
+}
+
+lazy_static! {
+    static ref SPEED_ENTRY_ID: text_input::Id = text_input::Id::unique();
 }
 
 impl Controls {
-    pub fn new() -> Controls {
+    /// Controls whose quantized speeds are the ones of `speed_table`
+    pub fn with_speed_table(speed_table: SpeedTable) -> Controls {
+        let speed_range = SpeedRange::default();
+        let speed = speed_table.quantized(speed_table.default_index()).unwrap();
+
         Controls {
-            background_color: Theme::default().palette().background,
+            theme: Theme::default(),
+            custom_theme: None,
+            theme_error: None,
             speed_mode: SpeedMode::Quantized,
-            speed_range: SpeedRange::default(),
-            speed: SpeedValue::Quantized(DEFAULT_QUANTIZED_SPEED_INDEX),
+            speed_range,
+            speed_normals: speed_table.normals(&speed_range),
+            markers: speed_table.markers(&speed_range),
+            speed,
+            smoothed_speed: SpeedSmoother::new(Ramp::Logarithmic, SPEED_RAMP_TIME, speed),
+            last_tick: None,
+            history: History::new(),
+            drag_start: None,
+            speed_table,
+            loop_region: (Normal::new(0.25), Normal::new(0.75)),
+            speed_entry: None,
+            last_readout_press: None,
+            presets: None,
+            midi_map: MidiMap::default(),
+            midi_learn: None,
+            osc: None,
+        }
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Controls {
+        if let Theme::Custom(custom_theme) = theme {
+            self.custom_theme = Some(custom_theme);
         }
+
+        self.theme = theme;
+        self
+    }
+
+    /// Sends every change of state to an OSC peer
+    pub fn with_osc_broadcaster(mut self, osc: OscBroadcaster) -> Controls {
+        self.osc = Some(osc);
+        self
+    }
+
+    pub fn with_midi_map(mut self, midi_map: MidiMap) -> Controls {
+        self.midi_map = midi_map;
+        self
+    }
+
+    /// The MIDI mappings, to save when MIDI learn changed them
+    pub fn midi_map(&self) -> &MidiMap {
+        &self.midi_map
+    }
+
+    /// Adds a panel to save, recall and morph the presets of `store`
+    pub fn with_presets(mut self, store: PresetStore) -> Controls {
+        let mut panel = PresetPanel {
+            store,
+            names: Vec::new(),
+            name: String::new(),
+            morph_from: None,
+            morph_to: None,
+            morph_amount: Normal::MIN,
+            error: None
+        };
+        panel.refresh_names();
+
+        self.presets = Some(panel);
+        self
+    }
+
+    /// The speed and mode to save for the next run
+    pub fn session(&self) -> Session {
+        Session::new(self.speed_mode, self.speed)
+    }
+
+    /// Picks up where a previous run left, without a glide or an undo step
+    pub fn restore_session(&mut self, session: &Session) {
+        self.speed_mode = session.speed_mode;
+        self.speed = session.speed_value(&self.speed_table, &self.speed_range);
+        self.smoothed_speed.snap(self.speed.as_f32());
+    }
+
+    pub fn theme(&self) -> Theme {
+        self.theme
     }
 
+    /// The clear colour of the window, which follows the theme
     pub fn background_color(&self) -> Color {
-        self.background_color
+        self.theme.palette().background
     }
-}
 
-impl Program for Controls {
-    type Message = Message;
-    type Renderer = Renderer<Theme>;
+    /// Whether the speed is still gliding and needs [`Message::Tick`]s
+    /// An operation that cancels the speed entry once it lost the keyboard
+    /// focus, to run after every update while it's open
+    ///
+    /// Escape and clicks elsewhere unfocus the entry, as does Tab.
+    pub fn speed_entry_blur_check(&self) -> Option<Box<dyn Operation<Message>>> {
+        self.speed_entry.as_ref().map(|_| -> Box<dyn Operation<Message>> {
+            Box::new(SpeedEntryBlurCheck { is_focused: false })
+        })
+    }
 
-    fn update(&mut self, message: Message) -> Command<Message> {
+    pub fn is_smoothing(&self) -> bool {
+        !self.smoothed_speed.is_settled()
+    }
+
+    /// Free speeds glide to their new value, quantized ones jump to it
+    fn set_speed(&mut self, speed: SpeedValue) {
+        self.speed = speed;
+        self.smoothed_speed.set_target(speed);
+
+        // time only counts from now if the speed was still
+        if self.is_smoothing() && self.last_tick.is_none() {
+            self.last_tick = Some(Instant::now());
+        }
+    }
+
+    /// The speed at `normal` on the speed sliders, one of the table in
+    /// quantized mode
+    fn speed_at(&self, normal: Normal) -> SpeedValue {
+        match self.speed_mode {
+            SpeedMode::Quantized => {
+                let index = self.speed_table.closest_index(normal, &self.speed_range);
+
+                self.speed_table.quantized(index).unwrap()
+            },
+            SpeedMode::Unquantized => {
+                SpeedValue::Unquantized(self.speed_range.unmap_to_value(normal))
+            }
+        }
+    }
+
+    fn preset(&self) -> Preset {
+        Preset {
+            speed_mode: self.speed_mode,
+            speed: self.speed.into(),
+            loop_region: self.loop_region
+        }
+    }
+
+    /// Recalling a preset is one undo step
+    fn apply_preset(&mut self, preset: &Preset) {
+        let before = self.undo_state();
+
+        self.speed_mode = preset.speed_mode;
+        self.set_speed(preset.speed.speed_value(&self.speed_table, &self.speed_range));
+        self.loop_region = preset.loop_region;
+
+        self.record(before);
+    }
+
+    /// Runs a preset operation and shows its error if it failed
+    fn with_presets_panel<T>(
+        &mut self,
+        operation: impl FnOnce(&mut PresetPanel) -> Result<T, PresetError>
+    ) -> Option<T> {
+        let panel = self.presets.as_mut()?;
+        let result = operation(panel);
+
+        panel.error = result.as_ref().err().map(ToString::to_string);
+        panel.refresh_names();
+
+        result.ok()
+    }
+
+    fn undo_state(&self) -> UndoState {
+        UndoState {
+            speed: self.speed,
+            speed_mode: self.speed_mode,
+            loop_region: self.loop_region
+        }
+    }
+
+    fn restore(&mut self, state: UndoState) {
+        self.speed_mode = state.speed_mode;
+        self.set_speed(state.speed);
+        self.loop_region = state.loop_region;
+    }
+
+    /// Records the change from `before` as one undo step, unless it is part
+    /// of a drag which is recorded as a whole when it ends
+    fn record(&mut self, before: UndoState) {
+        if self.drag_start.is_none() {
+            self.history.record(before, &self.undo_state());
+        }
+    }
+
+    /// The readout follows the smoothed speed
+    fn speed_text(&self) -> String {
+        match self.speed {
+            SpeedValue::Quantized { index, .. } => self.speed_table.speeds()[index].text.clone(),
+            SpeedValue::Unquantized(_) => format!("{:0>1.2}", self.smoothed_speed.value()),
+        }
+    }
+
+    fn apply(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::SetSpeed(speed) => {
-                self.speed = speed;
+                let before = self.undo_state();
+                self.set_speed(speed);
+                self.record(before);
+            },
+            Message::SetTheme(theme) => {
+                self.theme = theme;
+            },
+            // a reload only shows when the custom theme is picked, or when
+            // the file couldn't be read until now
+            Message::ThemeFileLoaded(Ok(custom_theme)) => {
+                if self.custom_theme.is_none() || matches!(self.theme, Theme::Custom(_)) {
+                    self.theme = Theme::Custom(custom_theme);
+                }
+
+                self.custom_theme = Some(custom_theme);
+                self.theme_error = None;
+            },
+            Message::ThemeFileLoaded(Err(error)) => {
+                self.theme_error = Some(error);
             },
             Message::SetSpeedMode(mode) => {
+                let before = self.undo_state();
                 self.speed_mode = mode;
+                self.record(before);
+            },
+            Message::SpeedGrabbed => {
+                self.drag_start = Some(self.undo_state());
+            },
+            Message::SpeedReleased => {
+                if let Some(before) = self.drag_start.take() {
+                    self.record(before);
+                }
+            },
+            Message::Undo => {
+                self.drag_start = None;
+
+                if let Some(state) = self.history.undo(self.undo_state()) {
+                    self.restore(state);
+                }
+            },
+            Message::Redo => {
+                self.drag_start = None;
+
+                if let Some(state) = self.history.redo(self.undo_state()) {
+                    self.restore(state);
+                }
+            },
+            Message::LoopRegionChanged(start, end) => {
+                self.loop_region = (start, end);
+            },
+            Message::ReadoutPressed => {
+                let now = Instant::now();
+                let is_double_click = self.last_readout_press
+                    .is_some_and(|last_press| now - last_press <= DOUBLE_CLICK_INTERVAL);
+
+                self.last_readout_press = Some(now);
+
+                if is_double_click && self.speed_entry.is_none() {
+                    self.speed_entry = Some(SpeedEntry {
+                        text: self.speed_text(),
+                        error: None
+                    });
+
+                    return Command::batch([
+                        text_input::focus(SPEED_ENTRY_ID.clone()),
+                        text_input::select_all(SPEED_ENTRY_ID.clone())
+                    ]);
+                }
+            },
+            Message::SpeedEntryChanged(text) => {
+                if let Some(entry) = &mut self.speed_entry {
+                    entry.text = text;
+                    entry.error = None;
+                }
+            },
+            Message::SpeedEntrySubmitted => {
+                if let Some(entry) = &mut self.speed_entry {
+                    match parse_speed(&entry.text, &self.speed_table, &self.speed_range) {
+                        Ok(speed) => {
+                            let before = self.undo_state();
+                            self.set_speed(speed);
+                            self.record(before);
+                            self.speed_entry = None;
+                        },
+                        // submitting an empty entry cancels the edit
+                        Err(ParseSpeedError::Empty) => {
+                            self.speed_entry = None;
+                        },
+                        Err(error) => {
+                            entry.error = Some(error);
+                        }
+                    }
+                }
+            },
+            Message::SpeedEntryCancelled => {
+                self.speed_entry = None;
+            },
+            Message::PresetNameChanged(name) => {
+                if let Some(panel) = &mut self.presets {
+                    panel.name = name;
+                    panel.error = None;
+                }
+            },
+            Message::SavePreset => {
+                let preset = self.preset();
+                self.with_presets_panel(|panel| panel.store.save(&panel.name, &preset));
+            },
+            Message::RecallPreset(name) => {
+                if let Some(preset) = self.with_presets_panel(|panel| panel.store.load(&name)) {
+                    self.apply_preset(&preset);
+                }
+            },
+            Message::RenamePreset(from) => {
+                self.with_presets_panel(|panel| {
+                    panel.store.rename(&from, &panel.name)?;
+
+                    for (name, _) in panel.morph_from.iter_mut().chain(panel.morph_to.iter_mut()) {
+                        if *name == from {
+                            name.clone_from(&panel.name);
+                        }
+                    }
+
+                    Ok(())
+                });
+            },
+            Message::DeletePreset(name) => {
+                self.with_presets_panel(|panel| {
+                    panel.store.delete(&name)?;
+
+                    for endpoint in [&mut panel.morph_from, &mut panel.morph_to] {
+                        if endpoint.as_ref().is_some_and(|(other, _)| *other == name) {
+                            *endpoint = None;
+                        }
+                    }
+
+                    Ok(())
+                });
+            },
+            Message::MorphFrom(name) => {
+                self.with_presets_panel(|panel| {
+                    panel.morph_from = Some((name.clone(), panel.store.load(&name)?));
+                    Ok(())
+                });
+            },
+            Message::MorphTo(name) => {
+                self.with_presets_panel(|panel| {
+                    panel.morph_to = Some((name.clone(), panel.store.load(&name)?));
+                    Ok(())
+                });
+            },
+            Message::MorphChanged(amount) => {
+                let (table, range) = (&self.speed_table, &self.speed_range);
+                let morphed = self.presets.as_mut().and_then(|panel| {
+                    panel.morph_amount = amount;
+
+                    match (&panel.morph_from, &panel.morph_to) {
+                        (Some((_, from)), Some((_, to))) => Some(from.morph(to, amount, table, range)),
+                        _ => None
+                    }
+                });
+
+                if let Some(preset) = morphed {
+                    self.apply_preset(&preset);
+                }
+            },
+            Message::MidiLearn(target) => {
+                self.midi_learn = if self.midi_learn == Some(target) { None } else { Some(target) };
+            },
+            // controllers move in many small steps, which aren't undo steps
+            Message::Midi(event) => {
+                if let Some(target) = self.midi_learn.take() {
+                    self.midi_map.bind(event.controller(), target);
+                } else if let Some(target) = self.midi_map.target(event.controller()) {
+                    match target {
+                        MidiTarget::Speed => self.set_speed(self.speed_at(event.normal())),
+                    }
+                }
+            },
+            // like MIDI, remote changes aren't undo steps
+            Message::Osc(command) => match command {
+                OscCommand::Speed(speed) => {
+                    let speed = match self.speed_mode {
+                        SpeedMode::Quantized => self.speed_at(self.speed_range.map_to_normal(speed)),
+                        SpeedMode::Unquantized => SpeedValue::Unquantized(self.speed_range.constrain(speed)),
+                    };
+                    self.set_speed(speed);
+                },
+                OscCommand::SpeedIndex(index) => {
+                    if let Some(speed) = usize::try_from(index).ok().and_then(|index| self.speed_table.quantized(index)) {
+                        self.set_speed(speed);
+                    }
+                },
+                OscCommand::SpeedMode(mode) => {
+                    self.speed_mode = mode;
+                }
+            },
+            Message::Tick(now) => {
+                if let Some(last_tick) = self.last_tick {
+                    self.smoothed_speed.advance(now.saturating_duration_since(last_tick));
+                }
+
+                self.last_tick = if self.is_smoothing() { Some(now) } else { None };
             }
         }
 
         Command::none()
     }
+}
+
+impl Program for Controls {
+    type Message = Message;
+    type Renderer = Renderer<Theme>;
+
+    /// Applies `message` then tells OSC peers what changed
+    fn update(&mut self, message: Message) -> Command<Message> {
+        let command = self.apply(message);
 
-    fn view(&self) -> Element<Message, Renderer<Theme>> {
+        if let Some(osc) = &mut self.osc {
+            if let Err(error) = osc.broadcast(OscState {
+                speed: self.speed.as_f32(),
+                index: match self.speed {
+                    SpeedValue::Quantized { index, .. } => Some(index),
+                    SpeedValue::Unquantized(_) => None,
+                },
+                speed_mode: self.speed_mode
+            }) {
+                eprintln!("Failed to broadcast over OSC: {}", error);
+            }
+        }
+
+        command
+    }
+
+    fn view(&self) -> Element<'_, Message, Renderer<Theme>> {
         let range = self.speed_range;
+        let (loop_start, loop_end) = self.loop_region;
 
-        let (speed_normal, display_value) = match self.speed {
-            SpeedValue::Quantized(index) => {
-                let quantized_speed = &QUANTIZED_SPEEDS[index];
-                let quantized_value = quantized_speed.numerator / quantized_speed.denominator;
+        let speed_normal = range.normal_param(self.speed.as_f32(), 1.);
 
-                (range.normal_param(quantized_value, 1.), quantized_speed.text.to_string())
+        let readout: Element<'_, Message, Renderer<Theme>> = match &self.speed_entry {
+            Some(entry) => {
+                TextInput::new("", &entry.text)
+                    .id(SPEED_ENTRY_ID.clone())
+                    .on_input(Message::SpeedEntryChanged)
+                    .on_submit(Message::SpeedEntrySubmitted)
+                    .style(if entry.error.is_some() { TextInputStyle::Error } else { TextInputStyle::Default })
+                    .size(14)
+                    .padding(2)
+                    .width(Length::Fixed(50.))
+                    .into()
             },
-            SpeedValue::Unquantized(value) => {
-                (range.normal_param(value, 1.), format!("{:0>1.2}", value))
+            None => {
+                MouseArea::new(
+                    Text::new(self.speed_text())
+                        .size(14)
+                        .width(Length::Fixed(50.))
+                        .horizontal_alignment(iced::alignment::Horizontal::Center)
+                )
+                .on_press(Message::ReadoutPressed)
+                .into()
             }
         };
 
-        let get_message = move |normal, opt_index| {
+        let ratios: Vec<Ratio> = self.speed_table.speeds().iter().map(|speed| speed.ratio).collect();
+
+        let get_message = move |normal, opt_index: Option<usize>| {
             match opt_index {
                 Some(index) => {
-                    Message::SetSpeed(SpeedValue::Quantized(index))
+                    Message::SetSpeed(SpeedValue::Quantized { index, ratio: ratios[index] })
                 },
                 None => {
                     Message::SetSpeed(SpeedValue::Unquantized(range.unmap_to_value(normal)))
@@ -86,89 +628,450 @@ impl Program for Controls {
         };
 
         let snappable_option = match self.speed_mode {
-            SpeedMode::Quantized => Some((QUANTIZED_SPEED_NORMALS.to_vec(), DEFAULT_QUANTIZED_SPEED_INDEX)),
+            SpeedMode::Quantized => Some((self.speed_normals.clone(), self.speed_table.default_index())),
             SpeedMode::Unquantized => None,
         };
 
+        let speed_style = match self.midi_learn {
+            Some(MidiTarget::Speed) => HSliderStyleType::Learning,
+            None => HSliderStyleType::Classic,
+        };
+
         let quantize_btn = Checkbox::new(
             "Quantised",
             self.speed_mode == SpeedMode::Quantized,
             |val| Message::SetSpeedMode(if val { SpeedMode::Quantized } else { SpeedMode::Unquantized })
         );
 
-        Column::new()
+        let themes = Theme::ALL.into_iter().chain(self.custom_theme.map(Theme::Custom));
+
+        let theme_picker = themes.fold(Row::new().spacing(8), |row, theme| {
+            row.push(
+                Button::new(Text::new(theme.name()).size(14))
+                    .on_press(Message::SetTheme(theme))
+                    .style(if theme == self.theme { ButtonStyle::Selected } else { ButtonStyle::Default })
+                    .padding([2, 8])
+            )
+        });
+
+        let content = Column::new().push(theme_picker);
+
+        let content = match &self.theme_error {
+            Some(error) => content.push(Text::new(error.as_str()).size(14).style(TextStyle::Error)),
+            None => content
+        };
+
+        let content = content
             .push(
                 Row::new()
                 .spacing(16)
                 .push(quantize_btn)
                 .push(
-                    HSlider::new(
-                        speed_normal,
-                        move |normal, index| get_message(normal, index),
+                    Column::new()
+                    .spacing(16)
+                    .push(
+                        HSlider::new(speed_normal, get_message.clone())
+                        .on_grab(Message::SpeedGrabbed)
+                        .on_release(Message::SpeedReleased)
+                        .on_right_click(Message::MidiLearn(MidiTarget::Speed))
+                        .style(speed_style)
+                        .snap_to_normals(snappable_option.clone())
+                        .markers(Some(self.markers.as_slice()))
+                        .fill_origin(FillOrigin::Default)
+                        .height(Length::Fixed(40.))
+                        .width(Length::Fixed(500.))
+                    )
+                    .push(
+                        HRangeSlider::new(
+                            NormalParam { value: loop_start, default: Normal::MIN },
+                            NormalParam { value: loop_end, default: Normal::MAX },
+                            |(start, _), (end, _)| Message::LoopRegionChanged(start, end),
+                        )
+                        .min_gap(0.05)
+                        .height(Length::Fixed(40.))
+                        .width(Length::Fixed(500.))
                     )
-                    .snap_to_normals(snappable_option)
-                    .markers(Some(MARKERS.as_slice()))
-                    .height(Length::Fixed(40.))
-                    .width(Length::Fixed(500.))
                 )
+                .push(readout)
                 .push(
-                    Text::new(display_value)
-                        .size(14)
-                        .width(Length::Fixed(30.))
-                        .horizontal_alignment(iced::alignment::Horizontal::Center)
+                    VSlider::new(speed_normal, get_message)
+                    .on_grab(Message::SpeedGrabbed)
+                    .on_release(Message::SpeedReleased)
+                    .on_right_click(Message::MidiLearn(MidiTarget::Speed))
+                    .style(speed_style)
+                    .snap_to_normals(snappable_option)
+                    .markers(Some(self.markers.as_slice()))
+                    .height(Length::Fixed(300.))
                 )
                 .align_items(Alignment::Center)
                 .width(Length::Shrink)
                 .height(Length::Fill)
-            )
-                .height(Length::Fill)
-                .width(Length::Fill)
-                .align_items(Alignment::Center)
-                .into()
+            );
+
+        let content = match &self.presets {
+            Some(panel) => content.push(panel.view()),
+            None => content
+        };
+
+        content
+            .padding(16)
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .align_items(Alignment::Center)
+            .into()
     }
 }
 
-fn generate_speed_normals() -> Vec<f32> {
-    QUANTIZED_SPEEDS
-        .into_iter()
-        .map(|speed_value| {
-            SpeedRange::default().map_to_normal(
-                speed_value.numerator / speed_value.denominator
-            ).as_f32()
-        })
-        .collect()
-}
+impl PresetPanel {
+    fn refresh_names(&mut self) {
+        match self.store.names() {
+            Ok(names) => self.names = names,
+            Err(error) => self.error = Some(error.to_string())
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message, Renderer<Theme>> {
+        let button = |label: &'static str, message: Message, style: ButtonStyle| {
+            Button::new(Text::new(label).size(14))
+                .on_press(message)
+                .style(style)
+                .padding([2, 8])
+        };
 
-fn generate_markers() -> Vec<(Normal, Option<String>, Option<MarkWeight>)> {
-    QUANTIZED_SPEEDS
-        .into_iter()
-        .map(|quantized_speed| {
-            (
-                SpeedRange::default().map_to_normal(
-                    quantized_speed.numerator / quantized_speed.denominator
-                ),
-                { if quantized_speed.text_mark.is_none() { None } else { Some(quantized_speed.text_mark.unwrap().to_string())} },
-                quantized_speed.mark_weight
+        let endpoint_style = |endpoint: &Option<(String, Preset)>, name: &String| {
+            match endpoint {
+                Some((other, _)) if other == name => ButtonStyle::Selected,
+                _ => ButtonStyle::Default
+            }
+        };
+
+        let list = self.names.iter().fold(Column::new().spacing(4), |list, name| {
+            list.push(
+                Row::new()
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .push(
+                    Button::new(Text::new(name.as_str()).size(14))
+                    .on_press(Message::RecallPreset(name.clone()))
+                    .padding([2, 8])
+                    .width(Length::Fixed(200.))
+                )
+                .push(button("A", Message::MorphFrom(name.clone()), endpoint_style(&self.morph_from, name)))
+                .push(button("B", Message::MorphTo(name.clone()), endpoint_style(&self.morph_to, name)))
+                .push(button("Rename", Message::RenamePreset(name.clone()), ButtonStyle::Default))
+                .push(button("Delete", Message::DeletePreset(name.clone()), ButtonStyle::Danger))
             )
-        })
-        .collect()
-}
+        });
 
-fn generate_tick_normals(numbers: Vec<f32>) -> Vec<Normal> {
-    numbers.into_iter()
-        .map(|value| {
-            // println!("value: {}", value);
-            // Normal::from_clipped(value)
-            Normal::new(value)
-        })
-        .collect()
+        let mut panel = Column::new()
+            .spacing(8)
+            .push(
+                Row::new()
+                .spacing(8)
+                .push(
+                    TextInput::new("Preset name", &self.name)
+                    .on_input(Message::PresetNameChanged)
+                    .on_submit(Message::SavePreset)
+                    .size(14)
+                    .padding(2)
+                    .width(Length::Fixed(200.))
+                )
+                .push(button("Save", Message::SavePreset, ButtonStyle::Default))
+            )
+            .push(list);
+
+        if let (Some((from, _)), Some((to, _))) = (&self.morph_from, &self.morph_to) {
+            panel = panel.push(
+                Row::new()
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .push(Text::new(from.as_str()).size(14))
+                .push(
+                    HSlider::new(
+                        NormalParam { value: self.morph_amount, default: Normal::MIN },
+                        |normal, _| Message::MorphChanged(normal)
+                    )
+                    .on_grab(Message::SpeedGrabbed)
+                    .on_release(Message::SpeedReleased)
+                    .height(Length::Fixed(40.))
+                    .width(Length::Fixed(300.))
+                )
+                .push(Text::new(to.as_str()).size(14))
+            );
+        }
+
+        if let Some(error) = &self.error {
+            panel = panel.push(Text::new(error.as_str()).size(14).style(TextStyle::Error));
+        }
+
+        panel.into()
+    }
 }
 
-lazy_static! {
-    #[derive(Debug)]
-    pub static ref QUANTIZED_SPEED_NORMALS: Vec<f32> = generate_speed_normals();
-    #[derive(Debug)]
-    pub static ref TICK_NORMALS: Vec<Normal> = generate_tick_normals(QUANTIZED_SPEED_NORMALS.to_vec());
-    #[derive(Debug)]
-    pub static ref MARKERS: Vec<(Normal, Option<String>, Option<MarkWeight>)> = generate_markers();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h_slider::harness::Harness;
+    use crate::midi::{Controller, MidiParser};
+    use iced_winit::core::widget::operation;
+    use iced_winit::core::{keyboard, Size};
+    use crate::osc::{OscArg, OscMessage};
+    use std::net::UdpSocket;
+
+    fn controls() -> Controls {
+        Controls::with_speed_table(SpeedTable::default())
+    }
+
+    fn update(controls: &mut Controls, message: Message) {
+        let _ = controls.update(message);
+    }
+
+    #[test]
+    fn the_background_follows_the_theme() {
+        let mut controls = controls();
+        assert_eq!(controls.background_color(), Theme::Dark.palette().background);
+
+        update(&mut controls, Message::SetTheme(Theme::Light));
+
+        assert_eq!(controls.theme(), Theme::Light);
+        assert_eq!(controls.background_color(), Theme::Light.palette().background);
+    }
+
+    #[test]
+    fn a_broken_theme_file_keeps_the_last_good_theme() {
+        let mut controls = controls();
+        let custom_theme = crate::theme_file::from_toml(
+            "background = \"#000\"\ntext = \"#fff\"\nprimary = \"#f80\"",
+        )
+        .unwrap();
+
+        update(&mut controls, Message::ThemeFileLoaded(Ok(custom_theme)));
+        update(&mut controls, Message::ThemeFileLoaded(Err("expected `=`".to_owned())));
+
+        assert_eq!(controls.theme(), Theme::Custom(custom_theme));
+        assert_eq!(controls.theme_error.as_deref(), Some("expected `=`"));
+
+        update(&mut controls, Message::ThemeFileLoaded(Ok(custom_theme)));
+        assert_eq!(controls.theme_error, None);
+
+        // reloads don't override a built-in theme picked since
+        update(&mut controls, Message::SetTheme(Theme::Light));
+        update(&mut controls, Message::ThemeFileLoaded(Ok(custom_theme)));
+        assert_eq!(controls.theme(), Theme::Light);
+    }
+
+    #[test]
+    fn a_drag_is_one_undo_step() {
+        let mut controls = controls();
+        let start = controls.speed;
+
+        update(&mut controls, Message::SpeedGrabbed);
+        for speed in [1.1, 1.2, 1.3] {
+            update(&mut controls, Message::SetSpeed(SpeedValue::Unquantized(speed)));
+        }
+        update(&mut controls, Message::SpeedReleased);
+
+        update(&mut controls, Message::Undo);
+        assert_eq!(controls.speed, start);
+
+        update(&mut controls, Message::Redo);
+        assert_eq!(controls.speed, SpeedValue::Unquantized(1.3));
+    }
+
+    #[test]
+    fn mode_toggles_are_undoable() {
+        let mut controls = controls();
+
+        update(&mut controls, Message::SetSpeedMode(SpeedMode::Unquantized));
+        update(&mut controls, Message::SetSpeed(SpeedValue::Unquantized(2.5)));
+
+        update(&mut controls, Message::Undo);
+        assert_eq!(controls.speed_mode, SpeedMode::Unquantized);
+        update(&mut controls, Message::Undo);
+        assert_eq!(controls.speed_mode, SpeedMode::Quantized);
+
+        // a new change drops what was undone
+        update(&mut controls, Message::SetSpeed(SpeedValue::Unquantized(0.5)));
+        update(&mut controls, Message::Redo);
+        assert_eq!(controls.speed, SpeedValue::Unquantized(0.5));
+    }
+
+    /// Whether the blur check of the open speed entry cancels it
+    fn cancels_the_entry(harness: &mut Harness<'_, Message>, controls: &Controls) -> bool {
+        let mut check = controls.speed_entry_blur_check().unwrap();
+        harness.operate(check.as_mut());
+
+        matches!(check.finish(), Outcome::Some(Message::SpeedEntryCancelled))
+    }
+
+    #[test]
+    fn the_speed_entry_closes_without_applying_when_it_loses_the_focus() {
+        let mut controls = controls();
+        let speed = controls.speed;
+
+        update(&mut controls, Message::ReadoutPressed);
+        update(&mut controls, Message::ReadoutPressed);
+        update(&mut controls, Message::SpeedEntryChanged("3/2".to_owned()));
+
+        let mut harness = Harness::new(controls.view(), Size::new(800., 600.));
+
+        // not focused yet, as when the user clicked elsewhere
+        assert!(cancels_the_entry(&mut harness, &controls));
+
+        harness.operate(&mut operation::focusable::focus(SPEED_ENTRY_ID.clone().into()));
+        assert!(!cancels_the_entry(&mut harness, &controls));
+
+        harness.key_press(keyboard::KeyCode::Escape, keyboard::Modifiers::default());
+        assert!(cancels_the_entry(&mut harness, &controls));
+        drop(harness);
+
+        update(&mut controls, Message::SpeedEntryCancelled);
+        assert!(controls.speed_entry.is_none());
+        assert_eq!(controls.speed, speed);
+    }
+
+    #[test]
+    fn presets_are_saved_recalled_and_morphed() {
+        let dir = std::env::temp_dir().join(format!("controls-presets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut controls = controls().with_presets(PresetStore::new(&dir));
+
+        for (name, speed) in [("one", 1.), ("four", 4.)] {
+            update(&mut controls, Message::SetSpeed(SpeedValue::Unquantized(speed)));
+            update(&mut controls, Message::PresetNameChanged(name.to_owned()));
+            update(&mut controls, Message::SavePreset);
+        }
+
+        assert_eq!(controls.presets.as_ref().unwrap().names, vec!["four", "one"]);
+
+        update(&mut controls, Message::RecallPreset("one".to_owned()));
+        assert_eq!(controls.speed, SpeedValue::Unquantized(1.));
+
+        update(&mut controls, Message::MorphFrom("one".to_owned()));
+        update(&mut controls, Message::MorphTo("four".to_owned()));
+        update(&mut controls, Message::MorphChanged(Normal::CENTER));
+        assert!((controls.speed.as_f32() - 2.).abs() < 1e-4);
+        assert!(matches!(controls.speed, SpeedValue::Quantized { .. }));
+
+        update(&mut controls, Message::DeletePreset("four".to_owned()));
+        let panel = controls.presets.as_ref().unwrap();
+        assert_eq!(panel.names, vec!["one"]);
+        assert!(panel.morph_to.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn undoing_a_recall_restores_the_loop_region() {
+        let dir = std::env::temp_dir().join(format!("controls-undo-presets-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut controls = controls().with_presets(PresetStore::new(&dir));
+        let start = controls.loop_region;
+
+        update(&mut controls, Message::LoopRegionChanged(Normal::new(0.1), Normal::new(0.2)));
+        update(&mut controls, Message::PresetNameChanged("short loop".to_owned()));
+        update(&mut controls, Message::SavePreset);
+        update(&mut controls, Message::LoopRegionChanged(start.0, start.1));
+
+        // the speed stays the same, the recall is still an undo step
+        update(&mut controls, Message::RecallPreset("short loop".to_owned()));
+        assert_eq!(controls.loop_region, (Normal::new(0.1), Normal::new(0.2)));
+
+        update(&mut controls, Message::Undo);
+        assert_eq!(controls.loop_region, start);
+
+        update(&mut controls, Message::Redo);
+        assert_eq!(controls.loop_region, (Normal::new(0.1), Normal::new(0.2)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Feeds raw MIDI bytes to the controls, as an input would
+    fn send_midi(controls: &mut Controls, parser: &mut MidiParser, bytes: &[u8]) {
+        if let Some(event) = parser.parse(bytes) {
+            update(controls, Message::Midi(event));
+        }
+    }
+
+    #[test]
+    fn midi_learn_binds_the_next_controller() {
+        let mut controls = controls();
+        let mut parser = MidiParser::new();
+
+        // unbound controllers do nothing
+        send_midi(&mut controls, &mut parser, &[0xB0, 1, 127]);
+        assert_eq!(controls.speed.as_f32(), 1.);
+
+        update(&mut controls, Message::MidiLearn(MidiTarget::Speed));
+        send_midi(&mut controls, &mut parser, &[0xB0, 1, 0]);
+        assert_eq!(controls.midi_map().target(Controller::Cc { channel: 0, controller: 1 }), Some(MidiTarget::Speed));
+        assert_eq!(controls.midi_learn, None);
+
+        // quantized speeds snap to the table
+        send_midi(&mut controls, &mut parser, &[0xB0, 1, 127]);
+        assert_eq!(controls.speed, controls.speed_table.quantized(controls.speed_table.len() - 1).unwrap());
+
+        send_midi(&mut controls, &mut parser, &[0xB0, 1, 70]);
+        assert!(matches!(controls.speed, SpeedValue::Quantized { .. }));
+    }
+
+    #[test]
+    fn nrpns_and_pitch_bends_drive_free_speeds() {
+        let mut controls = controls();
+        let mut parser = MidiParser::new();
+
+        update(&mut controls, Message::SetSpeedMode(SpeedMode::Unquantized));
+        update(&mut controls, Message::MidiLearn(MidiTarget::Speed));
+        for bytes in [[0xB2, 99, 0], [0xB2, 98, 7], [0xB2, 6, 0x40]] {
+            send_midi(&mut controls, &mut parser, &bytes);
+        }
+
+        // the middle of the range is a speed of 1
+        send_midi(&mut controls, &mut parser, &[0xB2, 38, 0]);
+        assert!((controls.speed.as_f32() - 1.).abs() < 1e-3);
+
+        send_midi(&mut controls, &mut parser, &[0xB2, 6, 0]);
+        assert_eq!(controls.speed, SpeedValue::Unquantized(0.125));
+
+        update(&mut controls, Message::MidiLearn(MidiTarget::Speed));
+        send_midi(&mut controls, &mut parser, &[0xE0, 0, 0]);
+        send_midi(&mut controls, &mut parser, &[0xE0, 0x7F, 0x7F]);
+        assert_eq!(controls.speed, SpeedValue::Unquantized(8.));
+    }
+
+    #[test]
+    fn osc_commands_drive_the_speed_and_are_broadcast() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut controls = controls()
+            .with_osc_broadcaster(OscBroadcaster::new(peer.local_addr().unwrap()).unwrap());
+
+        let receive = || {
+            let mut buffer = [0; 256];
+            let size = peer.recv(&mut buffer).unwrap();
+            OscMessage::decode(&buffer[..size]).unwrap().remove(0)
+        };
+
+        update(&mut controls, Message::Osc(OscCommand::Speed(2.)));
+        let index = match controls.speed {
+            SpeedValue::Quantized { index, ratio } if ratio.as_f32() == 2. => index,
+            speed => panic!("{:?} is not a quantized 2", speed),
+        };
+
+        assert_eq!(receive().address, "/speed/mode");
+        assert_eq!(receive(), OscMessage::new("/speed", vec![OscArg::Float(2.)]));
+        assert_eq!(receive(), OscMessage::new("/speed/index", vec![OscArg::Int(index as i32)]));
+
+        // out of the table
+        update(&mut controls, Message::Osc(OscCommand::SpeedIndex(-1)));
+        update(&mut controls, Message::Osc(OscCommand::SpeedMode(SpeedMode::Unquantized)));
+        update(&mut controls, Message::Osc(OscCommand::Speed(1.5)));
+
+        assert_eq!(controls.speed, SpeedValue::Unquantized(1.5));
+        assert_eq!(
+            receive(),
+            OscMessage::new("/speed/mode", vec![OscArg::String("unquantized".to_owned())])
+        );
+        assert_eq!(receive(), OscMessage::new("/speed", vec![OscArg::Float(1.5)]));
+    }
 }
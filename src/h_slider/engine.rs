@@ -0,0 +1,446 @@
+//! The orientation-independent slider engine shared by
+//! [`HSlider`](super::HSlider) and [`VSlider`](crate::v_slider::VSlider).
+//!
+//! Widgets only provide their geometry (rail and handle bounds) and an
+//! [`Orientation`], the engine takes care of dragging, snapping, wheel
+//! steps and modifier fine-tuning.
+
+pub use iced_core::widget::operation::Focusable;
+use iced_core::{event, keyboard, mouse, Event, Point, Rectangle, Shell};
+
+use super::normal::{Normal, NormalParam};
+use super::utils::{find_closest, SliderMove, SliderStatus};
+
+pub const DEFAULT_SCALAR: f32 = 0.9575;
+pub const DEFAULT_WHEEL_SCALAR: f32 = 0.01;
+pub const DEFAULT_MODIFIER_SCALAR: f32 = 0.02;
+pub const DEFAULT_KEYBOARD_STEP: f32 = 0.01;
+pub const DEFAULT_PAGE_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+impl Orientation {
+    /// Position of a point along the axis the slider moves on
+    fn axis_position(self, point: Point) -> f32 {
+        match self {
+            Orientation::Horizontal => point.x,
+            Orientation::Vertical => point.y,
+        }
+    }
+
+    /// Length of the bounds along the axis the slider moves on
+    fn axis_length(self, bounds: Rectangle) -> f32 {
+        match self {
+            Orientation::Horizontal => bounds.width,
+            Orientation::Vertical => bounds.height,
+        }
+    }
+
+    /// The normal under `point` on a rail, a vertical rail has its maximum at the top
+    fn normal_at(self, point: Point, rail_bounds: Rectangle) -> f32 {
+        match self {
+            Orientation::Horizontal => (point.x - rail_bounds.x) / rail_bounds.width,
+            Orientation::Vertical => 1.0 - (point.y - rail_bounds.y) / rail_bounds.height,
+        }
+    }
+
+    /// Normal delta for a drag of `distance` pixels, negative deltas increase the value
+    fn drag_delta(self, distance: f32, length: f32, scalar: f32) -> f32 {
+        match self {
+            Orientation::Horizontal => distance / length * -scalar,
+            Orientation::Vertical => distance / length * scalar,
+        }
+    }
+}
+
+/// Bounds of a slider widget, of its clickable rail area and of its handle
+#[derive(Debug, Clone, Copy)]
+pub struct SliderBounds {
+    pub bounds: Rectangle,
+    pub rail: Rectangle,
+    pub handle: Rectangle,
+}
+
+#[derive(Debug, Clone)]
+pub struct State {
+    is_dragging: bool,
+    prev_drag_position: f32,
+    continuous_normal: f32,
+    last_snapped_normal: Option<f32>,
+    pressed_modifiers: keyboard::Modifiers,
+    last_click: Option<mouse::Click>,
+    is_focused: bool,
+}
+
+impl State {
+    pub fn new(normal_param: NormalParam) -> Self {
+        Self {
+            is_dragging: false,
+            prev_drag_position: 0.0,
+            continuous_normal: normal_param.value.as_f32(),
+            last_snapped_normal: None,
+            pressed_modifiers: Default::default(),
+            last_click: None,
+            is_focused: false,
+        }
+    }
+}
+
+impl Focusable for State {
+    fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    fn focus(&mut self) {
+        self.is_focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.is_focused = false;
+    }
+}
+
+/// The parts of a slider widget that don't depend on how it's laid out
+pub struct SliderCore<'a, Message> {
+    pub normal_param: NormalParam,
+    pub on_change: Box<dyn Fn(Normal, Option<usize>) -> Message + 'a>,
+    /// Published when a drag of the handle starts
+    pub on_grab: Option<Message>,
+    /// Published when a drag of the handle ends
+    pub on_release: Option<Message>,
+    /// Published on a right click anywhere on the slider
+    pub on_right_click: Option<Message>,
+    pub scalar: f32,
+    pub wheel_scalar: f32,
+    pub modifier_scalar: f32,
+    pub modifier_keys: keyboard::Modifiers,
+    pub snap_normals: Option<(Vec<f32>, usize)>,
+    pub keyboard_step: f32,
+    pub page_step: f32,
+    pub orientation: Orientation,
+}
+
+impl<'a, Message: Clone> SliderCore<'a, Message> {
+    pub fn new<F>(normal_param: NormalParam, on_change: F, orientation: Orientation) -> Self
+    where
+        F: 'static + Fn(Normal, Option<usize>) -> Message,
+    {
+        SliderCore {
+            normal_param,
+            on_change: Box::new(on_change),
+            on_grab: None,
+            on_release: None,
+            on_right_click: None,
+            scalar: DEFAULT_SCALAR,
+            wheel_scalar: DEFAULT_WHEEL_SCALAR,
+            modifier_scalar: DEFAULT_MODIFIER_SCALAR,
+            modifier_keys: keyboard::Modifiers::CTRL,
+            snap_normals: None,
+            keyboard_step: DEFAULT_KEYBOARD_STEP,
+            page_step: DEFAULT_PAGE_STEP,
+            orientation,
+        }
+    }
+
+    pub fn move_virtual_slider(
+        &mut self,
+        state: &mut State,
+        messages: &mut Shell<'_, Message>,
+        slider_move: SliderMove,
+    ) {
+        match slider_move {
+            SliderMove::Default => {
+                self.normal_param.value = self.normal_param.default;
+                messages.publish((self.on_change)(self.normal_param.value, None));
+            }
+            SliderMove::Relative(delta) => match self.try_move_virtual_slider(state, delta) {
+                (SliderStatus::Moved, Some(index)) => {
+                    messages.publish((self.on_change)(self.normal_param.value, Some(index)));
+                }
+                (SliderStatus::Moved, None) => {
+                    messages.publish((self.on_change)(self.normal_param.value, None));
+                }
+                _ => {}
+            },
+            SliderMove::Step(steps) => {
+                let target = match &self.snap_normals {
+                    Some((normals, _)) => {
+                        let (index, _) = find_closest(self.normal_param.value.as_f32(), normals);
+                        let index = (index as i32 + steps).clamp(0, normals.len() as i32 - 1);
+                        normals[index as usize]
+                    }
+                    None => {
+                        let mut delta = steps as f32 * self.keyboard_step;
+
+                        if state.pressed_modifiers.contains(self.modifier_keys) {
+                            delta *= self.modifier_scalar;
+                        }
+
+                        self.normal_param.value.as_f32() + delta
+                    }
+                };
+
+                self.jump_virtual_slider(state, messages, target);
+            }
+            SliderMove::Page(pages) => {
+                let mut target = self.normal_param.value.as_f32() + pages as f32 * self.page_step;
+
+                if let Some((normals, _)) = &self.snap_normals {
+                    let (index, _) = find_closest(self.normal_param.value.as_f32(), normals);
+                    let (target_index, _) = find_closest(target, normals);
+
+                    // a page is never smaller than one snap index
+                    let target_index = if target_index == index {
+                        (index as i32 + pages.signum()).clamp(0, normals.len() as i32 - 1)
+                    } else {
+                        target_index as i32
+                    };
+
+                    target = normals[target_index as usize];
+                }
+
+                self.jump_virtual_slider(state, messages, target);
+            }
+            SliderMove::Absolute(target) => {
+                self.jump_virtual_slider(state, messages, target);
+            }
+        }
+    }
+
+    /// Moves straight to `target`, or to its closest snap normal
+    fn jump_virtual_slider(
+        &mut self,
+        state: &mut State,
+        messages: &mut Shell<'_, Message>,
+        target: f32,
+    ) {
+        let (next_normal, snap_index) = match &self.snap_normals {
+            Some((normals, _)) => {
+                let (snap_index, &snap_normal) = find_closest(target, normals);
+                state.last_snapped_normal = Some(snap_normal);
+
+                (Normal::from_clipped(snap_normal), Some(snap_index))
+            }
+            None => (Normal::from_clipped(target), None),
+        };
+
+        state.continuous_normal = next_normal.as_f32();
+
+        if next_normal != self.normal_param.value {
+            self.normal_param.update(next_normal);
+            messages.publish((self.on_change)(self.normal_param.value, snap_index));
+        }
+    }
+
+    fn try_move_virtual_slider(
+        &mut self,
+        state: &mut State,
+        delta: f32,
+    ) -> (SliderStatus, Option<usize>) {
+        let mut normal_delta = delta;
+
+        if normal_delta.abs() < f32::EPSILON {
+            return (SliderStatus::Unchanged, None);
+        }
+
+        if state.pressed_modifiers.contains(self.modifier_keys) {
+            normal_delta *= self.modifier_scalar;
+        }
+
+        let next_normal = Normal::from_clipped(state.continuous_normal - normal_delta);
+        state.continuous_normal = next_normal.as_f32();
+
+        match &self.snap_normals {
+            Some((normals, _)) => {
+                let (snap_index, &snap_normal) = find_closest(next_normal.as_f32(), normals);
+
+                // if snap value exists and we're not already snapped to it, snap to it
+                if state.last_snapped_normal != Some(snap_normal) {
+                    state.last_snapped_normal = Some(snap_normal);
+
+                    // the value may already sit on it before the first snap
+                    if self.normal_param.value.as_f32() != snap_normal {
+                        self.normal_param.value.set_clipped(snap_normal);
+                        return (SliderStatus::Moved, Some(snap_index));
+                    }
+                }
+
+                (SliderStatus::Unchanged, None)
+            }
+            None => {
+                self.normal_param.update(next_normal);
+                (SliderStatus::Moved, None)
+            }
+        }
+    }
+
+    pub fn on_event(
+        &mut self,
+        state: &mut State,
+        event: Event,
+        slider_bounds: SliderBounds,
+        cursor: mouse::Cursor,
+        messages: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let SliderBounds {
+            bounds,
+            rail: rail_bounds,
+            handle: handle_bounds,
+        } = slider_bounds;
+
+        match event {
+            Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::CursorMoved { .. } if state.is_dragging => {
+                    if let Some(cursor_position) = cursor.position() {
+                        let bounds_length = self.orientation.axis_length(bounds);
+
+                        if bounds_length > 0.0 {
+                            let position = self.orientation.axis_position(cursor_position);
+                            let normal_delta = self.orientation.drag_delta(
+                                position - state.prev_drag_position,
+                                bounds_length,
+                                self.scalar,
+                            );
+
+                            state.prev_drag_position = position;
+
+                            self.move_virtual_slider(
+                                state,
+                                messages,
+                                SliderMove::Relative(normal_delta),
+                            );
+
+                            return event::Status::Captured;
+                        }
+                    }
+                }
+                mouse::Event::WheelScrolled { delta } => {
+                    if self.wheel_scalar == 0.0 {
+                        return event::Status::Ignored;
+                    }
+
+                    if cursor.position_over(bounds).is_some() {
+                        let lines = match delta {
+                            mouse::ScrollDelta::Lines { y, .. } => y,
+                            mouse::ScrollDelta::Pixels { y, .. } => {
+                                if y > 0.0 {
+                                    1.0
+                                } else if y < 0.0 {
+                                    -1.0
+                                } else {
+                                    0.0
+                                }
+                            }
+                        };
+
+                        if lines != 0.0 {
+                            let normal_delta = -lines * self.wheel_scalar;
+                            self.move_virtual_slider(
+                                state,
+                                messages,
+                                SliderMove::Relative(normal_delta),
+                            );
+                            return event::Status::Captured;
+                        }
+                    }
+                }
+                mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                    state.is_focused = cursor.position_over(bounds).is_some();
+
+                    if let Some(cursor_position) = cursor.position_over(handle_bounds) {
+                        let click = mouse::Click::new(cursor_position, state.last_click);
+
+                        match click.kind() {
+                            mouse::click::Kind::Single => {
+                                state.is_dragging = true;
+                                state.prev_drag_position =
+                                    self.orientation.axis_position(cursor_position);
+
+                                if let Some(on_grab) = &self.on_grab {
+                                    messages.publish(on_grab.clone());
+                                }
+                            }
+                            _ => {
+                                state.is_dragging = false;
+                                self.move_virtual_slider(state, messages, SliderMove::Default);
+                            }
+                        }
+
+                        state.last_click = Some(click);
+
+                        return event::Status::Captured;
+                    } else if let Some(cursor_position) = cursor.position_over(rail_bounds) {
+                        let normal_delta = state.continuous_normal
+                            - self.orientation.normal_at(cursor_position, rail_bounds);
+                        self.move_virtual_slider(
+                            state,
+                            messages,
+                            SliderMove::Relative(normal_delta),
+                        );
+                        return event::Status::Captured;
+                    }
+                }
+                mouse::Event::ButtonPressed(mouse::Button::Right) => {
+                    if let Some(on_right_click) = &self.on_right_click {
+                        if cursor.position_over(bounds).is_some() {
+                            messages.publish(on_right_click.clone());
+                            return event::Status::Captured;
+                        }
+                    }
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                    if state.is_dragging {
+                        if let Some(on_release) = &self.on_release {
+                            messages.publish(on_release.clone());
+                        }
+                    }
+
+                    state.is_dragging = false;
+                    state.continuous_normal = self.normal_param.value.as_f32();
+
+                    return event::Status::Captured;
+                }
+                _ => {}
+            },
+            Event::Keyboard(keyboard_event) => match keyboard_event {
+                keyboard::Event::KeyPressed { key_code, modifiers } => {
+                    state.pressed_modifiers = modifiers;
+
+                    if state.is_focused {
+                        let slider_move = match key_code {
+                            keyboard::KeyCode::Right | keyboard::KeyCode::Up => SliderMove::Step(1),
+                            keyboard::KeyCode::Left | keyboard::KeyCode::Down => SliderMove::Step(-1),
+                            keyboard::KeyCode::PageUp => SliderMove::Page(1),
+                            keyboard::KeyCode::PageDown => SliderMove::Page(-1),
+                            keyboard::KeyCode::Home => SliderMove::Absolute(0.0),
+                            keyboard::KeyCode::End => SliderMove::Absolute(1.0),
+                            keyboard::KeyCode::Backspace => {
+                                SliderMove::Absolute(self.normal_param.default.as_f32())
+                            }
+                            _ => return event::Status::Ignored,
+                        };
+
+                        self.move_virtual_slider(state, messages, slider_move);
+                        state.continuous_normal = self.normal_param.value.as_f32();
+
+                        return event::Status::Captured;
+                    }
+                }
+                keyboard::Event::KeyReleased { modifiers, .. } => {
+                    state.pressed_modifiers = modifiers;
+                }
+                keyboard::Event::ModifiersChanged(modifiers) => {
+                    state.pressed_modifiers = modifiers;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        event::Status::Ignored
+    }
+}
@@ -6,6 +6,7 @@ use iced_widget::canvas::{
 
 use super::style::Appearance;
 use super::normal::Normal;
+use super::Marker;
 use crate::theme::RAIL_HANDLE_MARGIN;
 use crate::speed::MarkWeight;
 
@@ -16,47 +17,41 @@ pub fn draw_marks(
     handle_size: Size,
     text_mark_height: f32,
     rail_height: f32,
-    markers: Option<&[(Normal, Option<String>, Option<MarkWeight>)]>,
+    markers: Option<&[Marker]>,
 ) {
-    match markers {
-        Some(markers) => {
-            let rail_bounds: Rectangle = get_frame_rail_bounds(size, handle_size, text_mark_height, rail_height);
-            let marks_bounds = Rectangle {
-                x: rail_bounds.x - appearance.mark_width * 0.5,
-                y: rail_bounds.y,
-                width: rail_bounds.width,
-                height: rail_bounds.height
-            };
-
-            for (normal, _text, weight) in markers {
-                match weight {
-                    Some(weight) => {
-                        let mark_bounds = Rectangle {
-                            x: marks_bounds.x + normal.scale(marks_bounds.width),
-                            y: marks_bounds.y,
-                            width: appearance.mark_width,
-                            height: marks_bounds.height
-                        };
-
-                        let mark = Path::rectangle(mark_bounds.position(), mark_bounds.size());
-
-                        let mark_fill_color = match weight {
-                            MarkWeight::Normal => appearance.mark_color_normal,
-                            MarkWeight::Bold => appearance.mark_color_bold
-                        };
-
-                        let mark_fill = Fill {
-                            style: Style::Solid(mark_fill_color),
-                            ..Fill::default()
-                        };
-
-                        frame.fill(&mark, mark_fill);
-                    },
-                    None => {}
-                }
+    if let Some(markers) = markers {
+        let rail_bounds: Rectangle = get_frame_rail_bounds(size, handle_size, text_mark_height, rail_height);
+        let marks_bounds = Rectangle {
+            x: rail_bounds.x - appearance.mark_width * 0.5,
+            y: rail_bounds.y,
+            width: rail_bounds.width,
+            height: rail_bounds.height
+        };
+
+        for (normal, _text, weight) in markers {
+            if let Some(weight) = weight {
+                let mark_bounds = Rectangle {
+                    x: marks_bounds.x + normal.scale(marks_bounds.width),
+                    y: marks_bounds.y,
+                    width: appearance.mark_width,
+                    height: marks_bounds.height
+                };
+
+                let mark = Path::rectangle(mark_bounds.position(), mark_bounds.size());
+
+                let mark_fill_color = match weight {
+                    MarkWeight::Normal => appearance.mark_color_normal,
+                    MarkWeight::Bold => appearance.mark_color_bold
+                };
+
+                let mark_fill = Fill {
+                    style: Style::Solid(mark_fill_color),
+                    ..Fill::default()
+                };
+
+                frame.fill(&mark, mark_fill);
             }
-        },
-        None => {}
+        }
     }
 }
 
@@ -67,41 +62,33 @@ pub fn draw_text_marks(
     handle_size: Size,
     text_mark_height: f32,
     rail_height: f32,
-    markers: Option<&[(Normal, Option<String>, Option<MarkWeight>)]>
+    markers: Option<&[Marker]>
 ) {
-    match markers {
-        Some(markers) => {
-            let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_height, rail_height);
-
-            for (normal, text, _) in markers {
-                let mark_offset: f32 = rail_bounds.x + normal.scale(rail_bounds.width);
-
-                // println!("========== normal: {:?}", normal);
-
-                match text {
-                    Some(text) => {
-                        let text_mark = Text {
-                            content: text.to_string(),
-                            position: Point {
-                                x: mark_offset,
-                                y: 0.
-                            },
-                            color: appearance.text_mark_color,
-                            size: appearance.text_mark_size,
-                            font: appearance.text_mark_font,
-                            horizontal_alignment: Horizontal::Center,
-                            vertical_alignment: Vertical::Top,
-                            line_height: LineHeight::default(),
-                            shaping: Shaping::default()
-                        };
-
-                        frame.fill_text(text_mark);
+    if let Some(markers) = markers {
+        let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_height, rail_height);
+
+        for (normal, text, _) in markers {
+            let mark_offset: f32 = rail_bounds.x + normal.scale(rail_bounds.width);
+
+            if let Some(text) = text {
+                let text_mark = Text {
+                    content: text.to_string(),
+                    position: Point {
+                        x: mark_offset,
+                        y: 0.
                     },
-                    None => {}
-                }
+                    color: appearance.text_mark_color,
+                    size: appearance.text_mark_size,
+                    font: appearance.text_mark_font,
+                    horizontal_alignment: Horizontal::Center,
+                    vertical_alignment: Vertical::Top,
+                    line_height: LineHeight::default(),
+                    shaping: Shaping::default()
+                };
+
+                frame.fill_text(text_mark);
             }
-        },
-        None => {}
+        }
     }
 }
 
@@ -134,13 +121,15 @@ pub fn get_text_and_rail_bounds (
     }
 }
 
+/// Draws the rail, and the value fill between the two normals of `fill` if any
 pub fn draw_slider_rail(
     frame: &mut Frame,
     size: Size,
     appearance: Appearance,
     handle_size: Size,
     text_mark_height: f32,
-    rail_height: f32
+    rail_height: f32,
+    fill: Option<(Normal, Normal)>
 ) {
     let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_height, rail_height);
 
@@ -154,6 +143,30 @@ pub fn draw_slider_rail(
         &rail,
         rail_fill
     );
+
+    if let Some((origin, value)) = fill {
+        let (start, end) = if origin <= value { (origin, value) } else { (value, origin) };
+
+        let value_fill = Path::rectangle(
+            Point {
+                x: rail_bounds.x + start.scale(rail_bounds.width),
+                y: rail_bounds.y
+            },
+            Size {
+                width: (end.as_f32() - start.as_f32()) * rail_bounds.width,
+                height: rail_bounds.height
+            }
+        );
+        let value_fill_style = Fill {
+            style: Style::Solid(appearance.fill_color),
+            ..Fill::default()
+        };
+
+        frame.fill(
+            &value_fill,
+            value_fill_style
+        );
+    }
 }
 
 pub fn get_handle_position(
@@ -225,3 +238,4 @@ pub fn draw_handle(
         );
     });
 }
+
@@ -0,0 +1,109 @@
+//! Drives a widget with synthetic events, without a window or a GPU
+//!
+//! The widget is laid out once at a fixed size and keeps its [`Tree`] across
+//! events, messages published to the [`Shell`] are returned to the caller.
+
+use crate::theme::Theme;
+
+use iced::advanced::widget::Tree;
+use iced::{Element, Renderer};
+use iced_core::widget::Operation;
+use iced_core::{
+    clipboard, event, keyboard, layout, mouse, Event, Layout, Point, Shell, Size,
+};
+
+pub struct Harness<'a, Message> {
+    element: Element<'a, Message, Renderer<Theme>>,
+    tree: Tree,
+    node: layout::Node,
+    renderer: Renderer<Theme>,
+    cursor: mouse::Cursor,
+}
+
+impl<'a, Message> Harness<'a, Message> {
+    pub fn new(element: impl Into<Element<'a, Message, Renderer<Theme>>>, size: Size) -> Self {
+        let element = element.into();
+        let renderer = Renderer::TinySkia(iced_tiny_skia::Renderer::new(
+            iced_tiny_skia::Backend::new(iced_tiny_skia::Settings::default()),
+        ));
+
+        let node = element
+            .as_widget()
+            .layout(&renderer, &layout::Limits::new(Size::ZERO, size));
+        let tree = Tree::new(&element);
+
+        Self {
+            element,
+            tree,
+            node,
+            renderer,
+            cursor: mouse::Cursor::Unavailable,
+        }
+    }
+
+    pub fn event(&mut self, event: Event) -> (event::Status, Vec<Message>) {
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+
+        let status = self.element.as_widget_mut().on_event(
+            &mut self.tree,
+            event,
+            Layout::new(&self.node),
+            self.cursor,
+            &self.renderer,
+            &mut clipboard::Null,
+            &mut shell,
+            &self.node.bounds(),
+        );
+
+        (status, messages)
+    }
+
+    pub fn move_cursor(&mut self, position: Point) -> Vec<Message> {
+        self.cursor = mouse::Cursor::Available(position);
+
+        self.event(Event::Mouse(mouse::Event::CursorMoved { position })).1
+    }
+
+    pub fn press(&mut self) -> Vec<Message> {
+        self.event(Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))).1
+    }
+
+    pub fn release(&mut self) -> Vec<Message> {
+        self.event(Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))).1
+    }
+
+    /// Moves the cursor to `position` and clicks there
+    pub fn click(&mut self, position: Point) -> Vec<Message> {
+        let mut messages = self.move_cursor(position);
+        messages.extend(self.press());
+        messages.extend(self.release());
+        messages
+    }
+
+    pub fn scroll(&mut self, delta: mouse::ScrollDelta) -> Vec<Message> {
+        self.event(Event::Mouse(mouse::Event::WheelScrolled { delta })).1
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: keyboard::Modifiers) -> Vec<Message> {
+        self.event(Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers))).1
+    }
+
+    pub fn key_press(
+        &mut self,
+        key_code: keyboard::KeyCode,
+        modifiers: keyboard::Modifiers,
+    ) -> (event::Status, Vec<Message>) {
+        self.event(Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers }))
+    }
+
+    /// Runs `operation` over the widgets, as a command would
+    pub fn operate(&mut self, operation: &mut dyn Operation<Message>) {
+        self.element.as_widget().operate(
+            &mut self.tree,
+            Layout::new(&self.node),
+            &self.renderer,
+            operation,
+        );
+    }
+}
@@ -1,14 +1,22 @@
-use std::fmt::Debug;
-
 mod graphics;
 pub mod style;
 mod utils;
 pub mod normal;
+pub mod taper;
+pub mod engine;
+pub mod range;
+#[cfg(test)]
+pub(crate) mod harness;
+#[cfg(test)]
+mod snapshots;
+#[cfg(test)]
+mod tests;
 
 use style::StyleSheet;
 use normal::{Normal, NormalParam};
+use taper::TaperedRange;
 use graphics::*;
-use utils::*;
+use engine::{Focusable, Orientation, SliderBounds, SliderCore, State};
 
 use crate::theme::{DEFAULT_HANDLE_SIZE, DEFAULT_RAIL_HEIGHT, DEFAULT_TEXT_MARKER_HEIGHT};
 use crate::speed::MarkWeight;
@@ -25,31 +33,53 @@ use iced::Renderer;
 use iced_widget::canvas::{self, Frame, Cache};
 use iced::advanced::{
     renderer,
-    widget::{tree, Tree, Widget},
+    widget::{tree, Operation, Tree, Widget},
 };
 
 static DEFAULT_HEIGHT: u16 = 14;
-static DEFAULT_SCALAR: f32 = 0.9575;
-static DEFAULT_WHEEL_SCALAR: f32 = 0.01;
-static DEFAULT_MODIFIER_SCALAR: f32 = 0.02;
+
+/// A mark drawn on the rail at a [`Normal`], with an optional text label
+pub type Marker = (Normal, Option<String>, Option<MarkWeight>);
+
+/// Where the value fill of the rail starts from
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FillOrigin {
+    /// No value fill
+    #[default]
+    None,
+    /// From the start of the rail, for unipolar parameters
+    Min,
+    /// From the center of the rail, for bipolar parameters
+    Center,
+    /// From the default value of the [`NormalParam`]
+    Default,
+    Custom(Normal),
+}
+
+impl FillOrigin {
+    fn normal(self, normal_param: NormalParam) -> Option<Normal> {
+        match self {
+            FillOrigin::None => None,
+            FillOrigin::Min => Some(Normal::MIN),
+            FillOrigin::Center => Some(Normal::CENTER),
+            FillOrigin::Default => Some(normal_param.default),
+            FillOrigin::Custom(normal) => Some(normal),
+        }
+    }
+}
 
 #[allow(missing_debug_implementations)]
 pub struct HSlider<'a, Message, Theme>
 where
     Theme: StyleSheet,
 {
-    normal_param: NormalParam,
-    on_change: Box<dyn Fn(Normal, Option<usize>) -> Message + 'a>,
-    scalar: f32,
-    wheel_scalar: f32,
-    modifier_scalar: f32,
-    modifier_keys: keyboard::Modifiers,
+    core: SliderCore<'a, Message>,
     width: Length,
     height: Length,
     style: <Theme as StyleSheet>::Style,
     geometry_cache: Cache,
-    snap_normals: Option<(Vec<f32>, usize)>,
-    markers: Option<&'a [(Normal, Option<String>, Option<MarkWeight>)]>,
+    markers: Option<&'a [Marker]>,
+    fill_origin: FillOrigin,
     handle_size: Size,
     text_mark_height: f32,
     rail_height: f32,
@@ -65,24 +95,53 @@ where
         F: 'static + Fn(Normal, Option<usize>) -> Message,
     {
         HSlider {
-            normal_param,
-            on_change: Box::new(on_change),
-            scalar: DEFAULT_SCALAR,
-            wheel_scalar: DEFAULT_WHEEL_SCALAR,
-            modifier_scalar: DEFAULT_MODIFIER_SCALAR,
-            modifier_keys: keyboard::Modifiers::CTRL,
+            core: SliderCore::new(normal_param, on_change, Orientation::Horizontal),
             width: Length::Fill,
-            height: Length::from(Length::Fixed(DEFAULT_HEIGHT as f32)),
+            height: Length::Fixed(DEFAULT_HEIGHT as f32),
             style: Default::default(),
             geometry_cache: canvas::Cache::default(),
-            snap_normals: None,
             markers: None,
+            fill_origin: FillOrigin::default(),
             handle_size: DEFAULT_HANDLE_SIZE,
             text_mark_height: DEFAULT_TEXT_MARKER_HEIGHT,
             rail_height: DEFAULT_RAIL_HEIGHT,
         }
     }
 
+    /// A slider over the values of `range`, stepped ranges snap to each of
+    /// their values
+    pub fn with_range<R, F>(range: R, value: R::Value, default: R::Value, on_change: F) -> Self
+    where
+        R: 'static + TaperedRange,
+        F: 'static + Fn(R::Value) -> Message,
+    {
+        let normal_param = range.normal_param(value, default);
+        let snap_normals = range.snap_to(default);
+
+        Self::new(normal_param, move |normal, _| on_change(range.unmap_to_value(normal)))
+            .snap_to_normals(snap_normals)
+    }
+
+    /// Sets the message published when a drag of the handle starts
+    pub fn on_grab(mut self, on_grab: Message) -> Self {
+        self.core.on_grab = Some(on_grab);
+        self
+    }
+
+    /// Sets the message published when a drag of the handle ends, a whole
+    /// drag can then be handled as a single change
+    pub fn on_release(mut self, on_release: Message) -> Self {
+        self.core.on_release = Some(on_release);
+        self
+    }
+
+    /// Sets the message published on a right click, such as to start a
+    /// MIDI learn
+    pub fn on_right_click(mut self, on_right_click: Message) -> Self {
+        self.core.on_right_click = Some(on_right_click);
+        self
+    }
+
     pub fn width(mut self, width: Length) -> Self {
         self.width = width;
         self
@@ -99,38 +158,50 @@ where
     }
 
     pub fn modifier_keys(mut self, modifier_keys: keyboard::Modifiers) -> Self {
-        self.modifier_keys = modifier_keys;
+        self.core.modifier_keys = modifier_keys;
         self
     }
 
     pub fn scalar(mut self, scalar: f32) -> Self {
-        self.scalar = scalar;
+        self.core.scalar = scalar;
         self
     }
 
     pub fn wheel_scalar(mut self, wheel_scalar: f32) -> Self {
-        self.wheel_scalar = wheel_scalar;
+        self.core.wheel_scalar = wheel_scalar;
         self
     }
 
     pub fn modifier_scalar(mut self, scalar: f32) -> Self {
-        self.modifier_scalar = scalar;
+        self.core.modifier_scalar = scalar;
+        self
+    }
+
+    pub fn keyboard_step(mut self, keyboard_step: f32) -> Self {
+        self.core.keyboard_step = keyboard_step;
+        self
+    }
+
+    pub fn page_step(mut self, page_step: f32) -> Self {
+        self.core.page_step = page_step;
         self
     }
 
     pub fn snap_to_normals(mut self, snap_normals: Option<(Vec<f32>, usize)>) -> Self {
-        self.snap_normals = snap_normals;
+        self.core.snap_normals = snap_normals;
         self
     }
 
-    pub fn markers(
-        mut self,
-        markers: Option<&'a [(Normal, Option<String>, Option<MarkWeight>)]>,
-    ) -> Self {
+    pub fn markers(mut self, markers: Option<&'a [Marker]>) -> Self {
         self.markers = markers;
         self
     }
 
+    pub fn fill_origin(mut self, fill_origin: FillOrigin) -> Self {
+        self.fill_origin = fill_origin;
+        self
+    }
+
     pub fn handle_size(mut self, handle_size: Size) -> Self {
         self.handle_size = handle_size;
         self
@@ -145,102 +216,8 @@ where
         self.rail_height = rail_height;
         self
     }
-
-    fn move_virtual_slider(
-        &mut self,
-        state: &mut State,
-        messages: &mut Shell<'_, Message>,
-        slider_move: SliderMove,
-    ) {
-        match slider_move {
-            SliderMove::Default => {
-                self.normal_param.value = self.normal_param.default;
-                messages.publish((self.on_change)(self.normal_param.value, None));
-            }
-            SliderMove::Relative(delta) => match self.try_move_virtual_slider(state, delta) {
-                (SliderStatus::Moved, Some(index)) => {
-                    messages.publish((self.on_change)(self.normal_param.value, Some(index)));
-                }
-                (SliderStatus::Moved, None) => {
-                    messages.publish((self.on_change)(self.normal_param.value, None));
-                }
-                _ => {}
-            },
-        }
-    }
-
-    fn try_move_virtual_slider(
-        &mut self,
-        state: &mut State,
-        delta: f32,
-    ) -> (SliderStatus, Option<usize>) {
-        let mut normal_delta = delta;
-
-        if normal_delta.abs() < f32::EPSILON {
-            return (SliderStatus::Unchanged, None);
-        }
-
-        if state.pressed_modifiers.contains(self.modifier_keys) {
-            normal_delta *= self.modifier_scalar;
-        }
-
-        let next_normal = Normal::from_clipped(state.continuous_normal - normal_delta);
-        state.continuous_normal = next_normal.as_f32();
-
-        return match &self.snap_normals {
-            Some((normals, _)) => {
-                let (snap_index, &snap_normal) = find_closest(next_normal.as_f32(), normals);
-
-                // if snap value exists and we're not already snapped to it, snap to it
-                if state.last_snapped_normal.is_none()
-                    || state.last_snapped_normal.unwrap() != snap_normal
-                {
-                    self.normal_param.value.set_clipped(snap_normal);
-
-                    state.last_snapped_normal = Some(snap_normal);
-                    return (SliderStatus::Moved, Some(snap_index));
-                }
-
-                (SliderStatus::Unchanged, None)
-            }
-            None => {
-                self.normal_param.update(next_normal);
-                (SliderStatus::Moved, None)
-            }
-        };
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct State {
-    is_dragging: bool,
-    prev_drag_x: f32,
-    continuous_normal: f32,
-    last_snapped_normal: Option<f32>,
-    pressed_modifiers: keyboard::Modifiers,
-    last_click: Option<mouse::Click>,
 }
 
-// #[derive(Debug, Clone, Copy)]
-// enum DragSource {
-//     Handle,
-//     Rail,
-// }
-
-impl State {
-    pub fn new(normal_param: NormalParam) -> Self {
-        Self {
-            is_dragging: false,
-            prev_drag_x: 0.0,
-            continuous_normal: normal_param.value.as_f32(),
-            last_snapped_normal: None,
-            pressed_modifiers: Default::default(),
-            last_click: None,
-        }
-    }
-}
-
-
 impl<'a, Message, Theme> Widget<Message, Renderer<Theme>> for HSlider<'a, Message, Theme>
 where
     Message: Clone,
@@ -251,7 +228,7 @@ where
     }
 
     fn state(&self) -> tree::State {
-        tree::State::new(State::new(self.normal_param))
+        tree::State::new(State::new(self.core.normal_param))
     }
 
     fn width(&self) -> Length {
@@ -270,6 +247,18 @@ where
         layout::Node::new(size)
     }
 
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        _layout: Layout<'_>,
+        _renderer: &Renderer<Theme>,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        let state = tree.state.downcast_mut::<State>();
+
+        operation.focusable(state, None);
+    }
+
     fn on_event(
         &mut self,
         tree: &mut Tree,
@@ -282,142 +271,31 @@ where
         _: &Rectangle,
     ) -> event::Status {
         let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
 
-        match event {
-            Event::Mouse(mouse_event) => match mouse_event {
-                mouse::Event::CursorMoved { .. } => match state.is_dragging {
-                    true => {
-                        if let Some(cursor_position) = cursor.position() {
-                            let bounds_width = layout.bounds().width;
-
-                            if bounds_width > 0.0 {
-                                let normal_delta = (cursor_position.x - state.prev_drag_x)
-                                    / bounds_width
-                                    * -self.scalar;
-
-                                state.prev_drag_x = cursor_position.x;
-
-                                self.move_virtual_slider(
-                                    state,
-                                    messages,
-                                    SliderMove::Relative(normal_delta),
-                                );
-
-                                return event::Status::Captured;
-                            }
-                        }
-                    }
-                    false => {}
-                },
-                mouse::Event::WheelScrolled { delta } => {
-                    if self.wheel_scalar == 0.0 {
-                        return event::Status::Ignored;
-                    }
-
-                    if cursor.position_over(layout.bounds()).is_some() {
-                        let lines = match delta {
-                            mouse::ScrollDelta::Lines { y, .. } => y,
-                            mouse::ScrollDelta::Pixels { y, .. } => {
-                                if y > 0.0 {
-                                    1.0
-                                } else if y < 0.0 {
-                                    -1.0
-                                } else {
-                                    0.0
-                                }
-                            }
-                        };
-
-                        if lines != 0.0 {
-                            let normal_delta = -lines * self.wheel_scalar;
-                            self.move_virtual_slider(
-                                state,
-                                messages,
-                                SliderMove::Relative(normal_delta),
-                            );
-                            return event::Status::Captured;
-                        }
-                    }
-                }
-                mouse::Event::ButtonPressed(mouse::Button::Left) => {
-                    let bounds = layout.bounds();
-                    let rail_bounds = get_text_and_rail_bounds(
-                        bounds,
-                        self.handle_size,
-                        self.text_mark_height,
-                        self.rail_height,
-                    );
-                    let handle_bounds = get_handle_bounds(
-                        bounds,
-                        self.normal_param.value,
-                        self.handle_size,
-                        self.text_mark_height,
-                        self.rail_height,
-                    );
-
-                    if let Some(cursor_position) = cursor.position_over(handle_bounds) {
-                        let click = mouse::Click::new(cursor_position, state.last_click);
-
-                        match click.kind() {
-                            mouse::click::Kind::Single => {
-                                state.is_dragging = true;
-                                state.prev_drag_x = cursor_position.x;
-                            }
-                            _ => {
-                                state.is_dragging = false;
-                                self.move_virtual_slider(state, messages, SliderMove::Default);
-                            }
-                        }
-
-                        state.last_click = Some(click);
-
-                        return event::Status::Captured;
-                    } else if let Some(cursor_position) = cursor.position_over(rail_bounds) {
-                        let normal_delta = state.continuous_normal
-                                - ((cursor_position.x - rail_bounds.x) / rail_bounds.width);
-                            self.move_virtual_slider(
-                                state,
-                                messages,
-                                SliderMove::Relative(normal_delta),
-                            );
-                        return event::Status::Captured;
-                    }
-                }
-                mouse::Event::ButtonReleased(mouse::Button::Left) => {
-                    state.is_dragging = false;
-                    state.continuous_normal = self.normal_param.value.as_f32();
-
-                    return event::Status::Captured;
-                }
-                _ => {}
-            },
-            Event::Keyboard(keyboard_event) => match keyboard_event {
-                keyboard::Event::KeyPressed { modifiers, .. } => {
-                    state.pressed_modifiers = modifiers;
-
-                    return event::Status::Captured;
-                }
-                keyboard::Event::KeyReleased { modifiers, .. } => {
-                    state.pressed_modifiers = modifiers;
-
-                    return event::Status::Captured;
-                }
-                keyboard::Event::ModifiersChanged(modifiers) => {
-                    state.pressed_modifiers = modifiers;
-
-                    return event::Status::Captured;
-                }
-                _ => {}
-            },
-            _ => {}
-        }
+        let slider_bounds = SliderBounds {
+            bounds,
+            rail: get_text_and_rail_bounds(
+                bounds,
+                self.handle_size,
+                self.text_mark_height,
+                self.rail_height,
+            ),
+            handle: get_handle_bounds(
+                bounds,
+                self.core.normal_param.value,
+                self.handle_size,
+                self.text_mark_height,
+                self.rail_height,
+            ),
+        };
 
-        event::Status::Ignored
+        self.core.on_event(state, event, slider_bounds, cursor, messages)
     }
 
     fn draw(
         &self,
-        _tree: &Tree,
+        tree: &Tree,
         renderer: &mut Renderer<Theme>,
         theme: &Theme,
         _style: &renderer::Style,
@@ -428,8 +306,11 @@ where
         let bounds = layout.bounds();
         let size = bounds.size();
         let is_mouse_over = cursor.position_over(bounds).is_some();
+        let is_focused = tree.state.downcast_ref::<State>().is_focused();
 
-        let appearance = if is_mouse_over {
+        let appearance = if is_focused {
+            theme.focused(self.style)
+        } else if is_mouse_over {
             theme.hovered(self.style)
         } else {
             theme.active(self.style)
@@ -437,7 +318,10 @@ where
 
         let static_primitives = self.geometry_cache.draw(renderer, size, |frame| {
             draw_text_marks(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, self.markers);
-            draw_slider_rail(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height);
+            let normal_param = self.core.normal_param;
+            let fill = self.fill_origin.normal(normal_param).map(|origin| (origin, normal_param.value));
+
+            draw_slider_rail(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, fill);
             draw_marks(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, self.markers);
         });
 
@@ -447,7 +331,7 @@ where
         draw_handle(
             &mut dynamic_frame,
             size,
-            self.normal_param.value,
+            self.core.normal_param.value,
             appearance,
             self.handle_size,
             self.text_mark_height,
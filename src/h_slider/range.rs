@@ -0,0 +1,484 @@
+//! A horizontal slider with a min and a max handle on a single rail
+
+use super::graphics::*;
+use super::normal::{Normal, NormalParam};
+use super::style::StyleSheet;
+use super::utils::find_closest;
+use super::engine::{DEFAULT_MODIFIER_SCALAR, DEFAULT_SCALAR};
+use super::Marker;
+
+use crate::theme::{DEFAULT_HANDLE_SIZE, DEFAULT_RAIL_HEIGHT, DEFAULT_TEXT_MARKER_HEIGHT};
+
+use iced_core::{
+    event, keyboard, layout, mouse::{self, Cursor}, Vector,
+    Clipboard, Element, Event, Layout, Length, Point, Rectangle, Shell, Size,
+    Renderer as _,
+};
+
+// most generic iced renderer
+use iced::Renderer;
+
+use iced_widget::canvas::{self, Frame, Cache};
+use iced::advanced::{
+    renderer,
+    widget::{tree, Tree, Widget},
+};
+
+static DEFAULT_HEIGHT: u16 = 14;
+static DEFAULT_MIN_GAP: f32 = 0.0;
+
+/// A handle value along with its snap index
+pub type RangeBound = (Normal, Option<usize>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeDrag {
+    Min,
+    Max,
+    Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct State {
+    drag: Option<RangeDrag>,
+    prev_drag_x: f32,
+    continuous_normals: (f32, f32),
+    pressed_modifiers: keyboard::Modifiers,
+    last_click: Option<mouse::Click>,
+}
+
+impl State {
+    pub fn new(min_param: NormalParam, max_param: NormalParam) -> Self {
+        Self {
+            drag: None,
+            prev_drag_x: 0.0,
+            continuous_normals: (min_param.value.as_f32(), max_param.value.as_f32()),
+            pressed_modifiers: Default::default(),
+            last_click: None,
+        }
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct HRangeSlider<'a, Message, Theme>
+where
+    Theme: StyleSheet,
+{
+    min_param: NormalParam,
+    max_param: NormalParam,
+    on_change: Box<dyn Fn(RangeBound, RangeBound) -> Message + 'a>,
+    scalar: f32,
+    modifier_scalar: f32,
+    modifier_keys: keyboard::Modifiers,
+    min_gap: f32,
+    width: Length,
+    height: Length,
+    style: <Theme as StyleSheet>::Style,
+    geometry_cache: Cache,
+    snap_normals: Option<(Vec<f32>, usize)>,
+    markers: Option<&'a [Marker]>,
+    handle_size: Size,
+    text_mark_height: f32,
+    rail_height: f32,
+}
+
+impl<'a, Message, Theme> HRangeSlider<'a, Message, Theme>
+where
+    Message: Clone,
+    Theme: StyleSheet,
+{
+    pub fn new<F>(min_param: NormalParam, max_param: NormalParam, on_change: F) -> Self
+    where
+        F: 'static + Fn(RangeBound, RangeBound) -> Message,
+    {
+        HRangeSlider {
+            min_param,
+            max_param,
+            on_change: Box::new(on_change),
+            scalar: DEFAULT_SCALAR,
+            modifier_scalar: DEFAULT_MODIFIER_SCALAR,
+            modifier_keys: keyboard::Modifiers::CTRL,
+            min_gap: DEFAULT_MIN_GAP,
+            width: Length::Fill,
+            height: Length::Fixed(DEFAULT_HEIGHT as f32),
+            style: Default::default(),
+            geometry_cache: canvas::Cache::default(),
+            snap_normals: None,
+            markers: None,
+            handle_size: DEFAULT_HANDLE_SIZE,
+            text_mark_height: DEFAULT_TEXT_MARKER_HEIGHT,
+            rail_height: DEFAULT_RAIL_HEIGHT,
+        }
+    }
+
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: Length) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn style(mut self, style: impl Into<<Theme as StyleSheet>::Style>) -> Self {
+        self.style = style.into();
+        self
+    }
+
+    pub fn modifier_keys(mut self, modifier_keys: keyboard::Modifiers) -> Self {
+        self.modifier_keys = modifier_keys;
+        self
+    }
+
+    pub fn scalar(mut self, scalar: f32) -> Self {
+        self.scalar = scalar;
+        self
+    }
+
+    pub fn modifier_scalar(mut self, scalar: f32) -> Self {
+        self.modifier_scalar = scalar;
+        self
+    }
+
+    /// The smallest distance, in normal units, kept between the two handles
+    pub fn min_gap(mut self, min_gap: f32) -> Self {
+        self.min_gap = min_gap.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn snap_to_normals(mut self, snap_normals: Option<(Vec<f32>, usize)>) -> Self {
+        self.snap_normals = snap_normals;
+        self
+    }
+
+    pub fn markers(mut self, markers: Option<&'a [Marker]>) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    pub fn handle_size(mut self, handle_size: Size) -> Self {
+        self.handle_size = handle_size;
+        self
+    }
+
+    pub fn text_mark_height(mut self, text_mark_height: f32) -> Self {
+        self.text_mark_height = text_mark_height;
+        self
+    }
+
+    pub fn rail_height(mut self, rail_height: f32) -> Self {
+        self.rail_height = rail_height;
+        self
+    }
+
+    /// Snaps a continuous normal if snapping is enabled
+    fn snap(&self, normal: f32) -> RangeBound {
+        match &self.snap_normals {
+            Some((normals, _)) => {
+                let (snap_index, &snap_normal) = find_closest(normal, normals);
+                (Normal::from_clipped(snap_normal), Some(snap_index))
+            }
+            None => (Normal::from_clipped(normal), None),
+        }
+    }
+
+    /// Moves the handles to the given continuous normals, a snapped handle
+    /// that would cross the other one or break the minimum gap stays put
+    fn move_handles(
+        &mut self,
+        state: &mut State,
+        messages: &mut Shell<'_, Message>,
+        continuous_normals: (f32, f32),
+    ) {
+        state.continuous_normals = continuous_normals;
+
+        let (mut min, mut min_index) = self.snap(continuous_normals.0);
+        let (mut max, mut max_index) = self.snap(continuous_normals.1);
+
+        if max.as_f32() - min.as_f32() < self.min_gap - f32::EPSILON {
+            if min != self.min_param.value {
+                (min, min_index) = self.snap(self.min_param.value.as_f32());
+            }
+            if max != self.max_param.value {
+                (max, max_index) = self.snap(self.max_param.value.as_f32());
+            }
+        }
+
+        if min != self.min_param.value || max != self.max_param.value {
+            self.min_param.update(min);
+            self.max_param.update(max);
+
+            messages.publish((self.on_change)((min, min_index), (max, max_index)));
+        }
+    }
+
+    /// Continuous normals after moving the dragged part of the range by `delta`
+    fn dragged_normals(&self, state: &State, drag: RangeDrag, delta: f32) -> (f32, f32) {
+        let (min, max) = state.continuous_normals;
+
+        match drag {
+            RangeDrag::Min => ((min + delta).min(max - self.min_gap).max(0.0), max),
+            RangeDrag::Max => (min, (max + delta).max(min + self.min_gap).min(1.0)),
+            RangeDrag::Span => {
+                let delta = delta.max(-min).min(1.0 - max);
+                (min + delta, max + delta)
+            }
+        }
+    }
+
+    fn handle_bounds(&self, bounds: Rectangle, value: Normal) -> Rectangle {
+        get_handle_bounds(bounds, value, self.handle_size, self.text_mark_height, self.rail_height)
+    }
+}
+
+impl<'a, Message, Theme> Widget<Message, Renderer<Theme>> for HRangeSlider<'a, Message, Theme>
+where
+    Message: Clone,
+    Theme: StyleSheet,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::new(self.min_param, self.max_param))
+    }
+
+    fn width(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn height(&self) -> Length {
+        self.height
+    }
+
+    fn layout(&self, _renderer: &Renderer<Theme>, limits: &layout::Limits) -> layout::Node {
+        let limits = limits.width(self.width).height(self.height);
+
+        let size = limits.resolve(Size::ZERO);
+
+        layout::Node::new(size)
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer<Theme>,
+        _clipboard: &mut dyn Clipboard,
+        messages: &mut Shell<'_, Message>,
+        _: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
+
+        match event {
+            Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::CursorMoved { .. } => {
+                    if let (Some(drag), Some(cursor_position)) = (state.drag, cursor.position()) {
+                        if bounds.width > 0.0 {
+                            let mut normal_delta = (cursor_position.x - state.prev_drag_x)
+                                / bounds.width
+                                * self.scalar;
+
+                            if state.pressed_modifiers.contains(self.modifier_keys) {
+                                normal_delta *= self.modifier_scalar;
+                            }
+
+                            state.prev_drag_x = cursor_position.x;
+
+                            let normals = self.dragged_normals(state, drag, normal_delta);
+                            self.move_handles(state, messages, normals);
+
+                            return event::Status::Captured;
+                        }
+                    }
+                }
+                mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                    let rail_bounds = get_text_and_rail_bounds(
+                        bounds,
+                        self.handle_size,
+                        self.text_mark_height,
+                        self.rail_height,
+                    );
+                    let min_handle_bounds = self.handle_bounds(bounds, self.min_param.value);
+                    let max_handle_bounds = self.handle_bounds(bounds, self.max_param.value);
+
+                    let over_min = cursor.position_over(min_handle_bounds);
+                    let over_max = cursor.position_over(max_handle_bounds);
+
+                    let pressed_handle = match (over_min, over_max) {
+                        // overlapping handles, pick the one on the side of the cursor
+                        (Some(position), Some(_)) => {
+                            if position.x < min_handle_bounds.center_x() {
+                                Some((RangeDrag::Min, position))
+                            } else {
+                                Some((RangeDrag::Max, position))
+                            }
+                        }
+                        (Some(position), None) => Some((RangeDrag::Min, position)),
+                        (None, Some(position)) => Some((RangeDrag::Max, position)),
+                        (None, None) => None,
+                    };
+
+                    if let Some((handle, cursor_position)) = pressed_handle {
+                        let click = mouse::Click::new(cursor_position, state.last_click);
+
+                        match click.kind() {
+                            mouse::click::Kind::Single => {
+                                state.drag = Some(handle);
+                                state.prev_drag_x = cursor_position.x;
+                            }
+                            _ => {
+                                state.drag = None;
+
+                                let (min, max) = state.continuous_normals;
+                                let normals = match handle {
+                                    RangeDrag::Min => (
+                                        self.min_param.default.as_f32().min(max - self.min_gap),
+                                        max,
+                                    ),
+                                    _ => (
+                                        min,
+                                        self.max_param.default.as_f32().max(min + self.min_gap),
+                                    ),
+                                };
+
+                                self.move_handles(state, messages, normals);
+                            }
+                        }
+
+                        state.last_click = Some(click);
+
+                        return event::Status::Captured;
+                    } else if let Some(cursor_position) = cursor.position_over(rail_bounds) {
+                        let normal = (cursor_position.x - rail_bounds.x) / rail_bounds.width;
+                        let (min, max) = state.continuous_normals;
+
+                        if normal > min && normal < max {
+                            state.drag = Some(RangeDrag::Span);
+                            state.prev_drag_x = cursor_position.x;
+                        } else {
+                            // jump the closest handle to the cursor
+                            let drag = if normal <= min { RangeDrag::Min } else { RangeDrag::Max };
+                            let delta = match drag {
+                                RangeDrag::Min => normal - min,
+                                _ => normal - max,
+                            };
+
+                            let normals = self.dragged_normals(state, drag, delta);
+                            self.move_handles(state, messages, normals);
+                        }
+
+                        return event::Status::Captured;
+                    }
+                }
+                mouse::Event::ButtonReleased(mouse::Button::Left) if state.drag.is_some() => {
+                    state.drag = None;
+                    state.continuous_normals = (
+                        self.min_param.value.as_f32(),
+                        self.max_param.value.as_f32(),
+                    );
+
+                    return event::Status::Captured;
+                }
+                _ => {}
+            },
+            Event::Keyboard(
+                keyboard::Event::KeyPressed { modifiers, .. }
+                | keyboard::Event::KeyReleased { modifiers, .. }
+                | keyboard::Event::ModifiersChanged(modifiers),
+            ) => {
+                state.pressed_modifiers = modifiers;
+            }
+            _ => {}
+        }
+
+        event::Status::Ignored
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer<Theme>,
+        theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let size = bounds.size();
+        let is_mouse_over = cursor.position_over(bounds).is_some();
+
+        let appearance = if is_mouse_over {
+            theme.hovered(self.style)
+        } else {
+            theme.active(self.style)
+        };
+
+        let static_primitives = self.geometry_cache.draw(renderer, size, |frame| {
+            draw_text_marks(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, self.markers);
+            draw_slider_rail(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, Some((self.min_param.value, self.max_param.value)));
+            draw_marks(frame, size, appearance, self.handle_size, self.text_mark_height, self.rail_height, self.markers);
+        });
+
+        // frame for dynamic primitives
+        let mut dynamic_frame = Frame::new(renderer, size);
+
+        for value in [self.min_param.value, self.max_param.value] {
+            draw_handle(
+                &mut dynamic_frame,
+                size,
+                value,
+                appearance,
+                self.handle_size,
+                self.text_mark_height,
+                self.rail_height
+            );
+        }
+
+        renderer.with_translation(
+            Vector::new(bounds.x, bounds.y),
+            |renderer| {
+                use iced::advanced::graphics::geometry::Renderer as _;
+
+                renderer.draw(vec![
+                    static_primitives,
+                    dynamic_frame.into_geometry()
+                ]);
+            },
+        );
+    }
+
+    fn diff(&self, _tree: &mut Tree) {}
+
+    fn mouse_interaction(
+        &self,
+        _state: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer<Theme>,
+    ) -> mouse::Interaction {
+        let bounds = layout.bounds();
+        let cursor_position = cursor.position().unwrap_or(Point::ORIGIN);
+        if bounds.contains(cursor_position) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+impl<'a, Message, Theme> From<HRangeSlider<'a, Message, Theme>> for Element<'a, Message, Renderer<Theme>>
+where
+    Message: 'a + Clone,
+    Theme: 'a + StyleSheet,
+{
+    fn from(h_range_slider: HRangeSlider<'a, Message, Theme>) -> Self {
+        Element::new(h_range_slider)
+    }
+}
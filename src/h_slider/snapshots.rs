@@ -0,0 +1,226 @@
+//! Golden-image tests for the [`HSlider`] rendering
+//!
+//! Each case renders a single slider offscreen and compares it against a
+//! reference PNG in `tests/snapshots/h_slider`. Run with `UPDATE_SNAPSHOTS=1`
+//! to (re)write the references, failing renders are written next to the
+//! build artifacts in `target/snapshots` for inspection.
+//!
+//! Cases fail when no adapter is available, set `WGPU_BACKEND=gl` and
+//! `WGPU_ADAPTER_NAME=llvmpipe` (or lavapipe with vulkan) to run them on a
+//! software adapter, or `SKIP_GPU_SNAPSHOTS=1` to skip them explicitly.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use super::{FillOrigin, HSlider, Marker};
+use super::taper::TaperedRange;
+use crate::offscreen::{Offscreen, Snapshot};
+use crate::speed::{SpeedRange, SpeedTable};
+use crate::theme::Theme;
+
+use iced::{Element, Renderer};
+use iced_core::{mouse, Length, Point, Size};
+use iced_winit::runtime::{Command, Program};
+use lazy_static::lazy_static;
+use palette::{FromColor, Lab, Srgb};
+
+const SIZE: Size<u32> = Size::new(500, 44);
+
+lazy_static! {
+    static ref SPEED_TABLE: SpeedTable = SpeedTable::default();
+    static ref MARKERS: Vec<Marker> = SPEED_TABLE.markers(&SpeedRange::default());
+}
+
+/// CIE76 distance above which two pixels are told apart by a viewer
+const JUST_NOTICEABLE_DIFFERENCE: f32 = 2.3;
+
+/// Share of the pixels allowed to differ, absorbs antialiasing differences
+/// between adapters
+const MAX_DIFFERENT_PIXELS: f32 = 0.0025;
+
+struct SliderCase {
+    speed: f32,
+    markers: bool,
+    fill_origin: FillOrigin,
+}
+
+impl Program for SliderCase {
+    type Message = ();
+    type Renderer = Renderer<Theme>;
+
+    fn update(&mut self, _message: ()) -> Command<()> {
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, (), Renderer<Theme>> {
+        HSlider::new(SpeedRange::default().normal_param(self.speed, 1.), |_, _| ())
+            .snap_to_normals(Some((SPEED_TABLE.normals(&SpeedRange::default()), SPEED_TABLE.default_index())))
+            .markers(self.markers.then_some(MARKERS.as_slice()))
+            .fill_origin(self.fill_origin)
+            .width(Length::Fixed(SIZE.width as f32))
+            .height(Length::Fixed(SIZE.height as f32))
+            .into()
+    }
+}
+
+fn render(case: SliderCase, cursor: mouse::Cursor) -> Option<Snapshot> {
+    let mut offscreen = match Offscreen::new() {
+        Ok(offscreen) => offscreen,
+        Err(error) if std::env::var_os("SKIP_GPU_SNAPSHOTS").is_some() => {
+            eprintln!("skipping snapshot, no adapter: {}", error);
+            return None;
+        }
+        Err(error) => panic!(
+            "no adapter to render snapshots: {}, set SKIP_GPU_SNAPSHOTS=1 to skip them",
+            error
+        ),
+    };
+
+    let theme = Theme::default();
+    let snapshot = offscreen
+        .render(case, SIZE, 1.0, cursor, &theme, theme.palette().background)
+        .expect("Render snapshot");
+
+    Some(snapshot)
+}
+
+fn load_png(path: &PathBuf) -> Snapshot {
+    let decoder = png::Decoder::new(File::open(path).expect("Open reference"));
+    let mut reader = decoder.read_info().expect("Read reference header");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).expect("Read reference");
+
+    assert_eq!(info.color_type, png::ColorType::Rgba, "reference must be RGBA");
+
+    pixels.truncate(info.buffer_size());
+
+    Snapshot {
+        width: info.width,
+        height: info.height,
+        pixels,
+    }
+}
+
+/// Share of the pixels whose perceptual distance is above the threshold
+fn different_pixels(actual: &Snapshot, expected: &Snapshot) -> f32 {
+    let to_lab = |pixel: &[u8]| {
+        Lab::from_color(Srgb::new(pixel[0], pixel[1], pixel[2]).into_format::<f32>())
+    };
+
+    let different = actual
+        .pixels
+        .chunks(4)
+        .zip(expected.pixels.chunks(4))
+        .filter(|(actual, expected)| {
+            let (actual, expected) = (to_lab(actual), to_lab(expected));
+            let distance = ((actual.l - expected.l).powi(2)
+                + (actual.a - expected.a).powi(2)
+                + (actual.b - expected.b).powi(2))
+            .sqrt();
+
+            distance > JUST_NOTICEABLE_DIFFERENCE
+        })
+        .count();
+
+    different as f32 / (actual.width * actual.height) as f32
+}
+
+fn assert_snapshot(name: &str, case: SliderCase, cursor: mouse::Cursor) {
+    let Some(actual) = render(case, cursor) else {
+        return;
+    };
+
+    let reference = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots/h_slider")
+        .join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(reference.parent().unwrap()).unwrap();
+        actual.save_png(&reference).expect("Write reference");
+        return;
+    }
+
+    assert!(
+        reference.exists(),
+        "missing reference {} for snapshot {}, run with UPDATE_SNAPSHOTS=1 to write it",
+        reference.display(),
+        name
+    );
+
+    let expected = load_png(&reference);
+
+    let different = if (actual.width, actual.height) == (expected.width, expected.height) {
+        different_pixels(&actual, &expected)
+    } else {
+        1.0
+    };
+
+    if different > MAX_DIFFERENT_PIXELS {
+        let failures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/snapshots");
+        fs::create_dir_all(&failures).unwrap();
+
+        let actual_path = failures.join(format!("{}.actual.png", name));
+        actual.save_png(&actual_path).expect("Write failed render");
+
+        panic!(
+            "snapshot {} differs from its reference on {:.2}% of its pixels, see {}",
+            name,
+            different * 100.,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn rail_and_handle() {
+    assert_snapshot(
+        "rail_and_handle",
+        SliderCase { speed: 1., markers: false, fill_origin: FillOrigin::None },
+        mouse::Cursor::Unavailable,
+    );
+}
+
+#[test]
+fn markers_and_text_marks() {
+    assert_snapshot(
+        "markers_and_text_marks",
+        SliderCase { speed: 1., markers: true, fill_origin: FillOrigin::None },
+        mouse::Cursor::Unavailable,
+    );
+}
+
+#[test]
+fn handle_at_min() {
+    assert_snapshot(
+        "handle_at_min",
+        SliderCase { speed: 0.125, markers: true, fill_origin: FillOrigin::None },
+        mouse::Cursor::Unavailable,
+    );
+}
+
+#[test]
+fn handle_at_max() {
+    assert_snapshot(
+        "handle_at_max",
+        SliderCase { speed: 8., markers: true, fill_origin: FillOrigin::None },
+        mouse::Cursor::Unavailable,
+    );
+}
+
+#[test]
+fn bipolar_fill() {
+    assert_snapshot(
+        "bipolar_fill",
+        SliderCase { speed: 0.25, markers: true, fill_origin: FillOrigin::Default },
+        mouse::Cursor::Unavailable,
+    );
+}
+
+#[test]
+fn hovered() {
+    assert_snapshot(
+        "hovered",
+        SliderCase { speed: 2., markers: true, fill_origin: FillOrigin::None },
+        mouse::Cursor::Available(Point::new(250., 30.)),
+    );
+}
@@ -5,6 +5,7 @@ use iced_core::Color;
 pub struct Appearance {
     pub background_color: Color,
     pub rail_color: Color,
+    pub fill_color: Color,
     pub handle_color: Color,
     pub mark_color_normal: Color,
     pub mark_color_bold: Color,
@@ -20,4 +21,6 @@ pub trait StyleSheet {
     fn active(&self, style: Self::Style) -> Appearance;
 
     fn hovered(&self, style: Self::Style) -> Appearance;
+
+    fn focused(&self, style: Self::Style) -> Appearance;
 }
\ No newline at end of file
@@ -0,0 +1,323 @@
+//! Ranges of real values tapered onto a [`Normal`]
+//!
+//! A slider only deals in normals, a [`TaperedRange`] decides which curve
+//! they follow: linear, exponential for frequencies, decibels or discrete
+//! steps. [`SpeedRange`](crate::speed::SpeedRange) is the log2 range of the
+//! speed controls.
+
+use super::normal::{Normal, NormalParam};
+use super::utils::find_closest;
+
+/// A mapping between the values of a parameter and [`Normal`]s
+pub trait TaperedRange {
+    type Value: Copy;
+
+    fn map_to_normal(&self, value: Self::Value) -> Normal;
+
+    fn unmap_to_value(&self, normal: Normal) -> Self::Value;
+
+    fn normal_param(&self, value: Self::Value, default: Self::Value) -> NormalParam {
+        NormalParam {
+            value: self.map_to_normal(value),
+            default: self.map_to_normal(default),
+        }
+    }
+
+    /// Normals of every value a stepped range can take, `None` when the range
+    /// is continuous
+    fn snap_normals(&self) -> Option<Vec<f32>> {
+        None
+    }
+
+    /// The snap normals along with the index of the one closest to `default`,
+    /// ready to hand to a slider
+    fn snap_to(&self, default: Self::Value) -> Option<(Vec<f32>, usize)> {
+        self.snap_normals().map(|normals| {
+            let (index, _) = find_closest(self.map_to_normal(default).as_f32(), &normals);
+            (normals, index)
+        })
+    }
+}
+
+/// Linear, frequency, decibel and stepped ranges
+// no demo control uses these ranges yet
+#[allow(dead_code)]
+pub mod ranges {
+    use super::*;
+
+    /// A continuous range where equal normal steps are equal value steps
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct LinearRange {
+        min: f32,
+        max: f32,
+        span_recip: f32,
+    }
+
+    impl LinearRange {
+        pub fn new(min: f32, max: f32) -> Self {
+            assert!(max > min);
+
+            Self {
+                min,
+                max,
+                span_recip: 1.0 / (max - min),
+            }
+        }
+    }
+
+    impl TaperedRange for LinearRange {
+        type Value = f32;
+
+        fn map_to_normal(&self, value: f32) -> Normal {
+            Normal::from_clipped((value - self.min) * self.span_recip)
+        }
+
+        fn unmap_to_value(&self, normal: Normal) -> f32 {
+            self.min + normal.scale(self.max - self.min)
+        }
+    }
+
+    /// A continuous range where equal normal steps are equal ratios, such as
+    /// octaves of a frequency in Hz
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct FreqRange {
+        min: f32,
+        max: f32,
+        min_log: f32,
+        log_span: f32,
+    }
+
+    impl FreqRange {
+        pub fn new(min: f32, max: f32) -> Self {
+            assert!(min > 0.0 && max > min);
+
+            let min_log = min.log2();
+
+            Self {
+                min,
+                max,
+                min_log,
+                log_span: max.log2() - min_log,
+            }
+        }
+    }
+
+    impl TaperedRange for FreqRange {
+        type Value = f32;
+
+        fn map_to_normal(&self, value: f32) -> Normal {
+            let value = value.clamp(self.min, self.max);
+            Normal::from_clipped((value.log2() - self.min_log) / self.log_span)
+        }
+
+        fn unmap_to_value(&self, normal: Normal) -> f32 {
+            let value = 2.0_f32.powf(self.min_log + normal.scale(self.log_span));
+
+            // rounding may land a hair outside of the range at its ends
+            value.clamp(self.min, self.max)
+        }
+    }
+
+    /// A range of decibels, linear in dB above its floor
+    ///
+    /// The bottom of the range is silence: a normal of `0.0` is
+    /// [`f32::NEG_INFINITY`], as is any value at or below `min`.
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct DecibelRange {
+        min: f32,
+        max: f32,
+        span_recip: f32,
+    }
+
+    impl DecibelRange {
+        pub fn new(min: f32, max: f32) -> Self {
+            assert!(min.is_finite() && max > min);
+
+            Self {
+                min,
+                max,
+                span_recip: 1.0 / (max - min),
+            }
+        }
+    }
+
+    impl TaperedRange for DecibelRange {
+        type Value = f32;
+
+        fn map_to_normal(&self, value: f32) -> Normal {
+            if value <= self.min {
+                Normal::MIN
+            } else {
+                Normal::from_clipped((value - self.min) * self.span_recip)
+            }
+        }
+
+        fn unmap_to_value(&self, normal: Normal) -> f32 {
+            if normal == Normal::MIN {
+                f32::NEG_INFINITY
+            } else {
+                self.min + normal.scale(self.max - self.min)
+            }
+        }
+    }
+
+    /// A range of integers, evenly spread along the normal
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct IntRange {
+        min: i32,
+        max: i32,
+    }
+
+    impl IntRange {
+        pub fn new(min: i32, max: i32) -> Self {
+            assert!(max > min);
+
+            Self { min, max }
+        }
+
+        fn span(&self) -> f32 {
+            (self.max - self.min) as f32
+        }
+    }
+
+    impl TaperedRange for IntRange {
+        type Value = i32;
+
+        fn map_to_normal(&self, value: i32) -> Normal {
+            Normal::from_clipped((value.clamp(self.min, self.max) - self.min) as f32 / self.span())
+        }
+
+        fn unmap_to_value(&self, normal: Normal) -> i32 {
+            self.min + normal.scale(self.span()).round() as i32
+        }
+
+        fn snap_normals(&self) -> Option<Vec<f32>> {
+            Some(
+                (self.min..=self.max)
+                    .map(|value| self.map_to_normal(value).as_f32())
+                    .collect(),
+            )
+        }
+    }
+
+    /// A range over a list of variants, evenly spread along the normal in the
+    /// order they are given
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct EnumRange<T> {
+        variants: Vec<T>,
+    }
+
+    impl<T: Copy + PartialEq> EnumRange<T> {
+        pub fn new(variants: Vec<T>) -> Self {
+            assert!(variants.len() > 1);
+
+            Self { variants }
+        }
+
+        fn index_range(&self) -> IntRange {
+            IntRange::new(0, self.variants.len() as i32 - 1)
+        }
+    }
+
+    impl<T: Copy + PartialEq> TaperedRange for EnumRange<T> {
+        type Value = T;
+
+        /// Variants missing from the range map to the first one
+        fn map_to_normal(&self, value: T) -> Normal {
+            let index = self
+                .variants
+                .iter()
+                .position(|variant| *variant == value)
+                .unwrap_or(0);
+
+            self.index_range().map_to_normal(index as i32)
+        }
+
+        fn unmap_to_value(&self, normal: Normal) -> T {
+            self.variants[self.index_range().unmap_to_value(normal) as usize]
+        }
+
+        fn snap_normals(&self) -> Option<Vec<f32>> {
+            self.index_range().snap_normals()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ranges::*;
+    use super::*;
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn linear_round_trips(value in -100f32..=100.) {
+            let range = LinearRange::new(-100., 100.);
+            prop_assert!((range.unmap_to_value(range.map_to_normal(value)) - value).abs() < 1e-3);
+        }
+
+        #[test]
+        fn freq_round_trips(value in 20f32..=20_000.) {
+            let range = FreqRange::new(20., 20_000.);
+            let round_trip = range.unmap_to_value(range.map_to_normal(value));
+            prop_assert!(((round_trip - value) / value).abs() < 1e-5);
+        }
+
+        #[test]
+        fn decibel_round_trips(value in -59.9f32..=6.) {
+            let range = DecibelRange::new(-60., 6.);
+            prop_assert!((range.unmap_to_value(range.map_to_normal(value)) - value).abs() < 1e-3);
+        }
+
+        #[test]
+        fn int_round_trips(value in -12i32..=12) {
+            let range = IntRange::new(-12, 12);
+            prop_assert_eq!(range.unmap_to_value(range.map_to_normal(value)), value);
+        }
+    }
+
+    #[test]
+    fn freq_octaves_are_evenly_spread() {
+        let range = FreqRange::new(20., 20_480.);
+
+        assert_eq!(range.map_to_normal(20.), Normal::MIN);
+        assert!((range.map_to_normal(640.).as_f32() - 0.5).abs() < 1e-6);
+        assert_eq!(range.map_to_normal(20_480.), Normal::MAX);
+    }
+
+    #[test]
+    fn decibel_floor_is_silence() {
+        let range = DecibelRange::new(-60., 6.);
+
+        assert_eq!(range.unmap_to_value(Normal::MIN), f32::NEG_INFINITY);
+        assert_eq!(range.map_to_normal(f32::NEG_INFINITY), Normal::MIN);
+        assert_eq!(range.map_to_normal(-80.), Normal::MIN);
+        assert_eq!(range.unmap_to_value(Normal::MAX), 6.);
+    }
+
+    #[test]
+    fn int_snaps_to_every_value() {
+        let range = IntRange::new(1, 5);
+
+        assert_eq!(range.snap_normals(), Some(vec![0., 0.25, 0.5, 0.75, 1.]));
+        assert_eq!(range.unmap_to_value(Normal::new(0.3)), 2);
+        assert_eq!(range.snap_to(4), Some((vec![0., 0.25, 0.5, 0.75, 1.], 3)));
+    }
+
+    #[test]
+    fn enum_steps_through_variants() {
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        enum Shape {
+            Sine,
+            Triangle,
+            Square,
+        }
+
+        let range = EnumRange::new(vec![Shape::Sine, Shape::Triangle, Shape::Square]);
+
+        assert_eq!(range.map_to_normal(Shape::Triangle), Normal::CENTER);
+        assert_eq!(range.unmap_to_value(Normal::new(0.9)), Shape::Square);
+        assert_eq!(range.snap_normals(), Some(vec![0., 0.5, 1.]));
+    }
+}
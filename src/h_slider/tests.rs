@@ -0,0 +1,429 @@
+//! Interaction tests for the [`HSlider`] and the [`HRangeSlider`], driven
+//! through [`Harness`]
+//!
+//! The sliders are laid out at 500x44 with the default geometry: the rail
+//! spans x = 12..488 below 18px of text marks, the handle hangs 3px under
+//! the rail.
+
+use super::harness::Harness;
+use super::normal::{Normal, NormalParam};
+use super::range::{HRangeSlider, RangeBound};
+use super::taper::ranges::IntRange;
+use super::taper::TaperedRange;
+use super::HSlider;
+use crate::speed::{SpeedRange, SpeedTable};
+use crate::theme::Theme;
+
+use iced_core::{event, keyboard, mouse, Event, Length, Point, Size};
+use lazy_static::lazy_static;
+
+const SIZE: Size = Size::new(500., 44.);
+const RAIL_X: f32 = 12.;
+const RAIL_WIDTH: f32 = 476.;
+const RAIL_Y: f32 = 22.;
+const HANDLE_Y: f32 = 36.;
+
+type Changed = (Normal, Option<usize>);
+
+lazy_static! {
+    static ref SPEED_NORMALS: Vec<f32> = SpeedTable::default().normals(&SpeedRange::default());
+    static ref DEFAULT_SPEED_INDEX: usize = SpeedTable::default().default_index();
+}
+
+fn slider(snap: bool) -> Harness<'static, Changed> {
+    let slider: HSlider<'static, Changed, Theme> =
+        HSlider::new(SpeedRange::default().default_normal_param(), |normal, index| {
+            (normal, index)
+        })
+        .snap_to_normals(
+            snap.then(|| (SPEED_NORMALS.to_vec(), *DEFAULT_SPEED_INDEX)),
+        )
+        .width(Length::Fixed(SIZE.width))
+        .height(Length::Fixed(SIZE.height));
+
+    Harness::new(slider, SIZE)
+}
+
+fn range_slider(
+    min: f32,
+    max: f32,
+    min_gap: f32,
+    snap_normals: Option<(Vec<f32>, usize)>,
+) -> Harness<'static, (RangeBound, RangeBound)> {
+    let slider: HRangeSlider<'static, (RangeBound, RangeBound), Theme> = HRangeSlider::new(
+        NormalParam { value: Normal::from(min), default: Normal::MIN },
+        NormalParam { value: Normal::from(max), default: Normal::MAX },
+        |min, max| (min, max),
+    )
+    .min_gap(min_gap)
+    .snap_to_normals(snap_normals)
+    .width(Length::Fixed(SIZE.width))
+    .height(Length::Fixed(SIZE.height));
+
+    Harness::new(slider, SIZE)
+}
+
+/// Presses at `from`, moves the cursor by `offsets` one after the other and
+/// releases, returning the messages of every move
+fn drag(
+    harness: &mut Harness<'static, (RangeBound, RangeBound)>,
+    from: Point,
+    offsets: &[f32],
+) -> Vec<Vec<(RangeBound, RangeBound)>> {
+    harness.move_cursor(from);
+    assert!(harness.press().is_empty());
+
+    let mut x = from.x;
+    let messages = offsets
+        .iter()
+        .map(|offset| {
+            x += offset;
+            harness.move_cursor(Point::new(x, from.y))
+        })
+        .collect();
+
+    harness.release();
+    messages
+}
+
+fn drag_delta(offset: f32) -> f32 {
+    offset / SIZE.width * super::engine::DEFAULT_SCALAR
+}
+
+fn handle_at(normal: f32) -> Point {
+    Point::new(RAIL_X + normal * RAIL_WIDTH, HANDLE_Y)
+}
+
+fn closest_snap_index(normal: f32) -> usize {
+    SPEED_NORMALS
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (normal - **a).abs().total_cmp(&(normal - **b).abs()))
+        .map(|(index, _)| index)
+        .unwrap()
+}
+
+fn assert_close(actual: Normal, expected: f32) {
+    assert!(
+        (actual.as_f32() - expected).abs() < 1e-4,
+        "expected normal {}, got {}",
+        expected,
+        actual.as_f32()
+    );
+}
+
+#[test]
+fn drag_steps_across_snap_points() {
+    let mut harness = slider(true);
+    let start = handle_at(0.5);
+
+    harness.move_cursor(start);
+    assert!(harness.press().is_empty());
+
+    let mut last_index = *DEFAULT_SPEED_INDEX;
+    let mut visited = Vec::new();
+
+    for offset in (5..=200).step_by(5) {
+        let offset = offset as f32;
+        let messages = harness.move_cursor(Point::new(start.x + offset, start.y));
+        let continuous = 0.5 + offset / SIZE.width * super::engine::DEFAULT_SCALAR;
+        let index = closest_snap_index(continuous);
+
+        if index == last_index {
+            assert!(messages.is_empty(), "no message while within a snap point");
+        } else {
+            assert_eq!(messages, vec![(Normal::from(SPEED_NORMALS[index]), Some(index))]);
+            visited.push(index);
+            last_index = index;
+        }
+    }
+
+    harness.release();
+
+    assert!(visited.len() > 1, "drag crossed several snap points");
+    assert!(visited.windows(2).all(|pair| pair[1] == pair[0] + 1), "{:?}", visited);
+}
+
+#[test]
+fn drag_without_snapping_follows_the_cursor() {
+    let mut harness = slider(false);
+
+    harness.move_cursor(handle_at(0.5));
+    harness.press();
+
+    let messages = harness.move_cursor(Point::new(handle_at(0.5).x - 50., HANDLE_Y));
+    let expected = 0.5 - 50. / SIZE.width * super::engine::DEFAULT_SCALAR;
+
+    assert_eq!(messages.len(), 1);
+    assert_close(messages[0].0, expected);
+    assert_eq!(messages[0].1, None);
+
+    harness.release();
+
+    // moving after release doesn't drag anymore
+    assert!(harness.move_cursor(Point::new(0., HANDLE_Y)).is_empty());
+}
+
+#[test]
+fn double_click_resets_to_default() {
+    let mut harness = slider(false);
+
+    let messages = harness.click(Point::new(RAIL_X + 0.25 * RAIL_WIDTH, RAIL_Y));
+    assert_close(messages[0].0, 0.25);
+
+    let handle = handle_at(0.25);
+    assert!(harness.click(handle).is_empty());
+
+    let messages = harness.click(handle);
+    assert_eq!(messages.len(), 1);
+    assert_close(messages[0].0, 0.5);
+    assert_eq!(messages[0].1, None);
+}
+
+#[test]
+fn rail_click_jumps_to_the_cursor() {
+    let mut harness = slider(false);
+
+    let messages = harness.click(Point::new(RAIL_X + 0.25 * RAIL_WIDTH, RAIL_Y));
+    assert_eq!(messages.len(), 1);
+    assert_close(messages[0].0, 0.25);
+
+    // the text marks above the rail are clickable too
+    let messages = harness.click(Point::new(RAIL_X + 0.8 * RAIL_WIDTH, 4.));
+    assert_eq!(messages.len(), 1);
+    assert_close(messages[0].0, 0.8);
+}
+
+#[test]
+fn rail_click_snaps() {
+    let mut harness = slider(true);
+    let target = SPEED_NORMALS[2];
+
+    let messages = harness.click(Point::new(RAIL_X + target * RAIL_WIDTH, RAIL_Y));
+    assert_eq!(messages, vec![(Normal::from(target), Some(2))]);
+}
+
+#[test]
+fn wheel_steps() {
+    let mut harness = slider(false);
+
+    // the wheel only acts while hovered
+    assert!(harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: 1. }).is_empty());
+
+    harness.move_cursor(Point::new(100., RAIL_Y));
+
+    let messages = harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: 1. });
+    assert_close(messages[0].0, 0.51);
+
+    let messages = harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: -2. });
+    assert_close(messages[0].0, 0.49);
+
+    // pixel deltas count as a single line whatever their size
+    let messages = harness.scroll(mouse::ScrollDelta::Pixels { x: 0., y: 40. });
+    assert_close(messages[0].0, 0.50);
+}
+
+#[test]
+fn wheel_steps_through_snap_points() {
+    let mut harness = slider(true);
+    harness.move_cursor(Point::new(100., RAIL_Y));
+
+    let mut index = *DEFAULT_SPEED_INDEX;
+    let mut messages = Vec::new();
+
+    // a line is smaller than the gap between two snap points
+    while messages.is_empty() {
+        messages = harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: 1. });
+    }
+
+    index += 1;
+    assert_eq!(messages, vec![(Normal::from(SPEED_NORMALS[index]), Some(index))]);
+}
+
+#[test]
+fn modifier_fine_tunes_drag_and_wheel() {
+    let mut harness = slider(false);
+    let fine = super::engine::DEFAULT_MODIFIER_SCALAR;
+
+    harness.set_modifiers(keyboard::Modifiers::CTRL);
+    harness.move_cursor(handle_at(0.5));
+    harness.press();
+
+    let messages = harness.move_cursor(Point::new(handle_at(0.5).x + 100., HANDLE_Y));
+    let expected = 0.5 + 100. / SIZE.width * super::engine::DEFAULT_SCALAR * fine;
+    assert_close(messages[0].0, expected);
+
+    harness.release();
+
+    let messages = harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: 1. });
+    assert_close(messages[0].0, expected + 0.01 * fine);
+
+    harness.set_modifiers(keyboard::Modifiers::default());
+
+    let messages = harness.scroll(mouse::ScrollDelta::Lines { x: 0., y: 1. });
+    assert_close(messages[0].0, expected + 0.01 * fine + 0.01);
+}
+
+#[test]
+fn keys_only_act_when_focused() {
+    let mut harness = slider(true);
+    let none = keyboard::Modifiers::default();
+
+    let (status, messages) = harness.key_press(keyboard::KeyCode::Right, none);
+    assert_eq!(status, event::Status::Ignored);
+    assert!(messages.is_empty());
+
+    // a click anywhere on the slider focuses it
+    harness.click(handle_at(0.5));
+
+    let (status, messages) = harness.key_press(keyboard::KeyCode::Right, none);
+    let index = *DEFAULT_SPEED_INDEX + 1;
+    assert_eq!(status, event::Status::Captured);
+    assert_eq!(messages, vec![(Normal::from(SPEED_NORMALS[index]), Some(index))]);
+
+    let (_, messages) = harness.key_press(keyboard::KeyCode::Home, none);
+    assert_eq!(messages, vec![(Normal::from(SPEED_NORMALS[0]), Some(0))]);
+
+    // focus navigation is left to the application
+    let (status, _) = harness.key_press(keyboard::KeyCode::Tab, none);
+    assert_eq!(status, event::Status::Ignored);
+
+    // clicking elsewhere drops the focus
+    harness.click(Point::new(0., SIZE.height + 10.));
+    let (status, _) = harness.key_press(keyboard::KeyCode::End, none);
+    assert_eq!(status, event::Status::Ignored);
+}
+
+#[test]
+fn stepped_range_publishes_values() {
+    let slider: HSlider<'static, i32, Theme> =
+        HSlider::with_range(IntRange::new(0, 4), 2, 2, |value| value)
+            .width(Length::Fixed(SIZE.width))
+            .height(Length::Fixed(SIZE.height));
+    let mut harness = Harness::new(slider, SIZE);
+
+    assert_eq!(harness.click(Point::new(RAIL_X + 0.7 * RAIL_WIDTH, RAIL_Y)), vec![3]);
+    assert_eq!(harness.click(Point::new(RAIL_X + 0.05 * RAIL_WIDTH, RAIL_Y)), vec![0]);
+
+    let (_, messages) = harness.key_press(keyboard::KeyCode::Right, keyboard::Modifiers::default());
+    assert_eq!(messages, vec![1]);
+}
+
+#[test]
+fn drags_are_bracketed_by_grab_and_release() {
+    #[derive(Debug, Clone, PartialEq)]
+    enum Drag {
+        Grabbed,
+        Changed,
+        Released,
+    }
+
+    let slider: HSlider<'static, Drag, Theme> =
+        HSlider::new(SpeedRange::default().default_normal_param(), |_, _| Drag::Changed)
+            .on_grab(Drag::Grabbed)
+            .on_release(Drag::Released)
+            .width(Length::Fixed(SIZE.width))
+            .height(Length::Fixed(SIZE.height));
+    let mut harness = Harness::new(slider, SIZE);
+
+    harness.move_cursor(handle_at(0.5));
+    assert_eq!(harness.press(), vec![Drag::Grabbed]);
+    assert_eq!(harness.move_cursor(handle_at(0.6)), vec![Drag::Changed]);
+    assert_eq!(harness.release(), vec![Drag::Released]);
+
+    // a click on the rail is a single change, not a drag
+    harness.move_cursor(Point::new(RAIL_X + 0.1 * RAIL_WIDTH, RAIL_Y));
+    assert_eq!(harness.press(), vec![Drag::Changed]);
+    assert!(harness.release().is_empty());
+}
+
+#[test]
+fn right_click_publishes_its_message() {
+    let slider: HSlider<'static, Option<usize>, Theme> =
+        HSlider::new(SpeedRange::default().default_normal_param(), |_, index| index)
+            .on_right_click(Some(usize::MAX))
+            .width(Length::Fixed(SIZE.width))
+            .height(Length::Fixed(SIZE.height));
+    let mut harness = Harness::new(slider, SIZE);
+
+    harness.move_cursor(Point::new(RAIL_X, RAIL_Y));
+    let (status, messages) =
+        harness.event(Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)));
+
+    assert_eq!(status, event::Status::Captured);
+    assert_eq!(messages, vec![Some(usize::MAX)]);
+}
+
+#[test]
+fn range_handles_do_not_cross() {
+    let mut harness = range_slider(0.2, 0.6, 0., None);
+
+    let messages = drag(&mut harness, handle_at(0.2), &[400.]);
+    assert_eq!(messages[0].len(), 1);
+    let ((min, _), (max, _)) = messages[0][0];
+    assert_close(min, 0.6);
+    assert_close(max, 0.6);
+
+    let mut harness = range_slider(0.2, 0.6, 0., None);
+
+    let messages = drag(&mut harness, handle_at(0.6), &[-400.]);
+    let ((min, _), (max, _)) = messages[0][0];
+    assert_close(min, 0.2);
+    assert_close(max, 0.2);
+}
+
+#[test]
+fn range_handles_keep_the_minimum_gap() {
+    let mut harness = range_slider(0.2, 0.6, 0.1, None);
+
+    let messages = drag(&mut harness, handle_at(0.2), &[400.]);
+    let ((min, _), (max, _)) = messages[0][0];
+    assert_close(min, 0.5);
+    assert_close(max, 0.6);
+
+    // the max handle is already as close as it can be
+    let messages = drag(&mut harness, handle_at(0.6), &[-400.]);
+    assert!(messages[0].is_empty());
+}
+
+#[test]
+fn range_span_drag_is_clamped_at_both_ends() {
+    let mut harness = range_slider(0.2, 0.6, 0., None);
+    let span = Point::new(RAIL_X + 0.4 * RAIL_WIDTH, RAIL_Y);
+
+    let messages = drag(&mut harness, span, &[100., 300., -1000.]);
+
+    // the span keeps its width while moving
+    let ((min, _), (max, _)) = messages[0][0];
+    assert_close(min, 0.2 + drag_delta(100.));
+    assert_close(max, 0.6 + drag_delta(100.));
+
+    let ((min, _), (max, _)) = messages[1][0];
+    assert_close(min, 0.6);
+    assert_close(max, 1.);
+
+    let ((min, _), (max, _)) = messages[2][0];
+    assert_close(min, 0.);
+    assert_close(max, 0.4);
+}
+
+#[test]
+fn range_handles_snap_on_their_own() {
+    let normals = IntRange::new(0, 4).snap_normals().unwrap();
+    let mut harness = range_slider(0.25, 0.75, 0., Some((normals, 0)));
+
+    let messages = drag(&mut harness, handle_at(0.25), &[50., 50.]);
+
+    // within the snap point the min handle started from
+    assert!(messages[0].is_empty());
+    assert_eq!(
+        messages[1],
+        vec![((Normal::from(0.5), Some(2)), (Normal::from(0.75), Some(3)))]
+    );
+
+    let messages = drag(&mut harness, handle_at(0.75), &[150.]);
+    assert_eq!(
+        messages[0],
+        vec![((Normal::from(0.5), Some(2)), (Normal::from(1.), Some(4)))]
+    );
+}
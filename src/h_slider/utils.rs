@@ -2,6 +2,11 @@
 pub enum SliderMove {
     Default,
     Relative(f32),
+    /// A number of snap indices, or of keyboard steps when not snapping
+    Step(i32),
+    /// A number of page steps, always at least one snap index when snapping
+    Page(i32),
+    Absolute(f32),
 }
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
@@ -11,9 +16,9 @@ pub enum SliderStatus {
     Unchanged,
 }
 
-pub fn find_closest(value: f32, candidates: &Vec<f32>) -> (usize, &f32) {
+pub fn find_closest(value: f32, candidates: &[f32]) -> (usize, &f32) {
     candidates
-        .into_iter()
+        .iter()
         .enumerate()
         .min_by(|(_, x), (_, y)| (value - *x).abs().partial_cmp(&(value - *y).abs()).unwrap())
         .unwrap()
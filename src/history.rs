@@ -0,0 +1,112 @@
+//! Undo and redo stacks of snapshots of some state
+
+/// Snapshots older than this are dropped
+const DEFAULT_LIMIT: usize = 100;
+
+/// Past and undone states of a `T`
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+    limit: usize,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        assert!(limit > 0);
+
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Records `before`, the state as it was before a change to `after`
+    ///
+    /// Nothing is recorded when the change left the state as it was, and
+    /// anything undone can't be redone anymore.
+    pub fn record(&mut self, before: T, after: &T) {
+        if before == *after {
+            return;
+        }
+
+        if self.undo_stack.len() == self.limit {
+            self.undo_stack.remove(0);
+        }
+
+        self.undo_stack.push(before);
+        self.redo_stack.clear();
+    }
+
+    /// The state to go back to from `current`, if any
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(previous)
+    }
+
+    /// The state undone from `current`, if any
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        Some(next)
+    }
+}
+
+impl<T: Clone + PartialEq> Default for History<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut history = History::new();
+        history.record(1, &2);
+        history.record(2, &3);
+
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), None);
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), Some(3));
+        assert_eq!(history.redo(3), None);
+    }
+
+    #[test]
+    fn recording_drops_the_redo_stack() {
+        let mut history = History::new();
+        history.record(1, &2);
+        history.undo(2);
+
+        history.record(1, &5);
+
+        assert_eq!(history.redo(5), None);
+        assert_eq!(history.undo(5), Some(1));
+    }
+
+    #[test]
+    fn skips_unchanged_states_and_keeps_the_latest() {
+        let mut history = History::with_limit(2);
+        history.record(1, &1);
+
+        assert_eq!(history.undo(1), None);
+
+        history.record(1, &2);
+        history.record(2, &3);
+        history.record(3, &4);
+
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), None);
+    }
+}
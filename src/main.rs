@@ -1,32 +1,101 @@
 mod color_utils;
+mod contrast;
 mod controls;
 mod h_slider;
+mod history;
+mod midi;
+mod offscreen;
+mod osc;
+mod preset;
+mod session;
 pub mod speed;
 mod theme;
-
-use controls::Controls;
+mod theme_file;
+mod v_slider;
+
+use color_utils::MIN_TEXT_CONTRAST;
+use controls::{Controls, Message};
+use midi::MidiMap;
+use offscreen::Offscreen;
+use osc::{OscBroadcaster, OscReceiver};
+use preset::PresetStore;
+use session::{Session, SessionSaver};
+use speed::SpeedTable;
 use theme::Theme;
+use theme_file::ThemeWatcher;
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use iced_wgpu::graphics::Viewport;
 use iced_wgpu::{wgpu, Backend, Settings};
 
+use iced_winit::core::keyboard;
 use iced_winit::core::mouse;
 use iced_winit::core::renderer;
-use iced_winit::core::{Color, Size};
+use iced_winit::core::widget::operation::{self, Operation};
+use iced_winit::core::Size;
+use iced_winit::runtime::command::Action;
 use iced_winit::runtime::program;
 use iced_winit::runtime::Debug;
 use iced_winit::{conversion, futures, winit, Clipboard};
 
 use winit::{
     event::{Event, ModifiersState, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoopBuilder},
 };
 
+const DEFAULT_SCREENSHOT_SIZE: Size<u32> = Size::new(1024, 768);
+/// Time between two frames of a speed glide
+const SMOOTHING_FRAME: Duration = Duration::from_millis(16);
+
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
+    let args = parse_args(std::env::args().skip(1))?;
+
+    let speed_table = match &args.speed_table {
+        Some(path) => match SpeedTable::load(path) {
+            Ok(speed_table) => speed_table,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        },
+        None => SpeedTable::default(),
+    };
+
+    // Render a single frame to a PNG file instead of opening a window
+    if let Some(Screenshot { path, size }) = args.screenshot {
+        let mut controls = Controls::with_speed_table(speed_table);
+
+        if let Some(path) = &args.theme {
+            match theme_file::load(path) {
+                Ok(custom_theme) => controls = controls.with_theme(Theme::Custom(custom_theme)),
+                Err(error) => {
+                    eprintln!("{}: {}", path.display(), error);
+                    std::process::exit(1);
+                }
+            }
+        }
+
+        let theme = controls.theme();
+        let background_color = controls.background_color();
+
+        let snapshot = Offscreen::new()?.render(
+            controls,
+            size,
+            1.0,
+            mouse::Cursor::Unavailable,
+            &theme,
+            background_color,
+        )?;
+
+        return snapshot.save_png(path);
+    }
+
     // Initialize winit
-    let event_loop = EventLoop::new();
+    let event_loop = EventLoopBuilder::<Message>::with_user_event().build();
     let window = winit::window::Window::new(&event_loop)?;
 
     let physical_size = window.inner_size();
@@ -97,8 +166,94 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut resized = false;
 
-    // Initialize scene and GUI controls
-    let controls = Controls::new();
+    // Initialize scene and GUI controls, as the previous run left them
+    let mut controls = Controls::with_speed_table(speed_table);
+
+    if let Some(dir) = PresetStore::default_dir() {
+        controls = controls.with_presets(PresetStore::new(dir));
+    }
+
+    let midi_map_path = MidiMap::default_path();
+
+    if let Some(path) = &midi_map_path {
+        match MidiMap::load(path) {
+            Ok(midi_map) => controls = controls.with_midi_map(midi_map),
+            Err(error) => eprintln!("{}: {}", path.display(), error),
+        }
+    }
+
+    let mut saved_midi_map = controls.midi_map().clone();
+
+    // MIDI events and OSC commands are sent to the event loop from the
+    // threads reading them
+    #[cfg(feature = "midi")]
+    let _midi_inputs = {
+        let proxy = event_loop.create_proxy();
+
+        midi::connect_inputs(move |event| {
+            let _ = proxy.send_event(Message::Midi(event));
+        })
+        .unwrap_or_else(|error| {
+            eprintln!("Failed to read MIDI inputs: {}", error);
+            Vec::new()
+        })
+    };
+
+    let _osc_receiver = match args.osc_port {
+        Some(port) => {
+            let proxy = event_loop.create_proxy();
+
+            let receiver = OscReceiver::spawn(("127.0.0.1", port), move |command| {
+                let _ = proxy.send_event(Message::Osc(command));
+            })?;
+            println!("listening for OSC on {}", receiver.local_addr());
+
+            Some(receiver)
+        }
+        None => None,
+    };
+
+    // The theme file is read again on every change, a broken one is reported
+    // in the window and the last good theme is kept
+    let _theme_watcher = match &args.theme {
+        Some(path) => {
+            let proxy = event_loop.create_proxy();
+            let theme_path = path.clone();
+            let send = move |theme: Result<_, theme_file::ThemeFileError>| {
+                if let Ok(custom_theme) = &theme {
+                    for issue in contrast::check(&Theme::Custom(*custom_theme), MIN_TEXT_CONTRAST) {
+                        eprintln!("{}: {}", theme_path.display(), issue);
+                    }
+                }
+
+                let message = Message::ThemeFileLoaded(theme.map_err(|error| error.to_string()));
+                let _ = proxy.send_event(message);
+            };
+
+            send(theme_file::load(path));
+
+            ThemeWatcher::spawn(path.clone(), send)
+                .map_err(|error| eprintln!("Failed to watch {}: {}", path.display(), error))
+                .ok()
+        }
+        None => None,
+    };
+
+    if let Some(port) = args.osc_send_port {
+        controls = controls.with_osc_broadcaster(OscBroadcaster::new(([127, 0, 0, 1], port).into())?);
+    }
+
+    let session_path = Session::default_path();
+
+    if let Some(path) = &session_path {
+        match Session::load(path) {
+            Ok(Some(session)) => controls.restore_session(&session),
+            Ok(None) => {}
+            Err(error) => eprintln!("{}: {}", path.display(), error),
+        }
+    }
+
+    let mut session_saver = session_path.map(|path| SessionSaver::new(path, controls.session()));
 
     // Initialize iced
     let mut debug = Debug::new();
@@ -122,7 +277,48 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
         // You should change this if you want to render continuosly
         *control_flow = ControlFlow::Wait;
 
+        let now = Instant::now();
+
+        // but keep waking up while the speed glides
+        if state.program().is_smoothing() {
+            *control_flow = ControlFlow::WaitUntil(now + SMOOTHING_FRAME);
+        }
+
+        // and save the session once it stops changing
+        if let Some(saver) = &mut session_saver {
+            saver.observe(state.program().session(), now);
+
+            if let Err(error) = saver.save_if_due(now) {
+                eprintln!("Failed to save the session: {}", error);
+            }
+
+            if let Some(deadline) = saver.deadline() {
+                if let ControlFlow::WaitUntil(wake_up) = *control_flow {
+                    *control_flow = ControlFlow::WaitUntil(wake_up.min(deadline));
+                } else {
+                    *control_flow = ControlFlow::WaitUntil(deadline);
+                }
+            }
+        }
+
+        // save the mappings as soon as a MIDI learn changes them
+        if *state.program().midi_map() != saved_midi_map {
+            saved_midi_map = state.program().midi_map().clone();
+
+            if let Some(path) = &midi_map_path {
+                if let Err(error) = saved_midi_map.save(path) {
+                    eprintln!("Failed to save the MIDI mappings: {}", error);
+                }
+            }
+        }
+
         match event {
+            Event::UserEvent(message) => {
+                state.queue_message(message);
+            }
+            Event::NewEvents(_) if state.program().is_smoothing() => {
+                state.queue_message(Message::Tick(Instant::now()));
+            }
             Event::WindowEvent { event, .. } => {
                 match event {
                     WindowEvent::CursorMoved { position, .. } => {
@@ -147,28 +343,89 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
                     state.queue_event(event);
                 }
             }
-            Event::MainEventsCleared => {
-                // If there are events pending
-                if !state.is_queue_empty() {
-                    // We update iced
+            // If there are events pending
+            Event::MainEventsCleared if !state.is_queue_empty() => {
+                let cursor = cursor_position
+                    .map(|p| conversion::cursor_position(p, viewport.scale_factor()))
+                    .map(mouse::Cursor::Available)
+                    .unwrap_or(mouse::Cursor::Unavailable);
+                let theme = state.program().theme();
+
+                // We update iced
+                let (uncaptured_events, command) = state.update(
+                    viewport.logical_size(),
+                    cursor,
+                    &mut renderer,
+                    &theme,
+                    &renderer::Style {
+                        text_color: theme.palette().text,
+                    },
+                    &mut clipboard,
+                    &mut debug,
+                );
+
+                // Ctrl+Z and Ctrl+Shift+Z undo and redo if no widget used them
+                let history_messages: Vec<_> = uncaptured_events
+                    .iter()
+                    .filter_map(history_message)
+                    .collect();
+                let has_history_messages = !history_messages.is_empty();
+
+                for message in history_messages {
+                    state.queue_message(message);
+                }
+
+                // Tab and Shift+Tab move the keyboard focus if no widget used them,
+                // widget operations requested by the controls are run, and an
+                // unfocused speed entry is closed
+                let operations: Vec<_> = uncaptured_events
+                    .iter()
+                    .filter_map(focus_operation)
+                    .chain(
+                        command
+                            .into_iter()
+                            .flat_map(|command| command.actions())
+                            .filter_map(|action| match action {
+                                Action::Widget(operation) => Some(operation),
+                                _ => None,
+                            }),
+                    )
+                    // last, once the focus has moved
+                    .chain(state.program().speed_entry_blur_check())
+                    .collect();
+
+                let has_operations = !operations.is_empty();
+
+                if has_operations {
+                    state.operate(
+                        &mut renderer,
+                        operations.into_iter(),
+                        viewport.logical_size(),
+                        &mut debug,
+                    );
+                }
+
+                // the frame was drawn with the theme from before the update
+                let theme_changed = state.program().theme() != theme;
+                let theme = state.program().theme();
+
+                if has_history_messages || has_operations || theme_changed {
+                    // and draw their result
                     let _ = state.update(
                         viewport.logical_size(),
-                        cursor_position
-                            .map(|p| conversion::cursor_position(p, viewport.scale_factor()))
-                            .map(mouse::Cursor::Available)
-                            .unwrap_or(mouse::Cursor::Unavailable),
+                        cursor,
                         &mut renderer,
-                        &Theme::Dark,
+                        &theme,
                         &renderer::Style {
-                            text_color: Color::WHITE,
+                            text_color: theme.palette().text,
                         },
                         &mut clipboard,
                         &mut debug,
                     );
-
-                    // and request a redraw
-                    window.request_redraw();
                 }
+
+                // and request a redraw
+                window.request_redraw();
             }
             Event::RedrawRequested(_) => {
                 if resized {
@@ -270,7 +527,111 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                 }
             }
+            Event::LoopDestroyed => {
+                if let Some(saver) = &mut session_saver {
+                    if let Err(error) = saver.flush() {
+                        eprintln!("Failed to save the session: {}", error);
+                    }
+                }
+            }
             _ => {}
         }
     })
 }
+
+fn focus_operation(event: &iced_winit::core::Event) -> Option<Box<dyn Operation<Message>>> {
+    match event {
+        iced_winit::core::Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code: keyboard::KeyCode::Tab,
+            modifiers,
+        }) => {
+            if modifiers.shift() {
+                Some(Box::new(operation::focusable::focus_previous()))
+            } else {
+                Some(Box::new(operation::focusable::focus_next()))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn history_message(event: &iced_winit::core::Event) -> Option<Message> {
+    match event {
+        iced_winit::core::Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code: keyboard::KeyCode::Z,
+            modifiers,
+        }) if modifiers.command() => {
+            if modifiers.shift() {
+                Some(Message::Redo)
+            } else {
+                Some(Message::Undo)
+            }
+        }
+        _ => None,
+    }
+}
+
+struct Screenshot {
+    path: PathBuf,
+    size: Size<u32>,
+}
+
+struct Args {
+    screenshot: Option<Screenshot>,
+    speed_table: Option<PathBuf>,
+    osc_port: Option<u16>,
+    osc_send_port: Option<u16>,
+    theme: Option<PathBuf>,
+}
+
+/// Parses `[--speeds <path>] [--theme <path>] [--osc <port>] [--osc-send <port>]
+/// [--screenshot <path> [--size <width>x<height>]]`
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut size = DEFAULT_SCREENSHOT_SIZE;
+    let mut speed_table = None;
+    let mut osc_port = None;
+    let mut osc_send_port = None;
+    let mut theme = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--screenshot" => {
+                path = Some(PathBuf::from(args.next().ok_or("--screenshot needs a path")?));
+            }
+            "--size" => {
+                let value = args.next().ok_or("--size needs a value")?;
+                let (width, height) = value
+                    .split_once('x')
+                    .ok_or_else(|| format!("invalid size \"{}\", expected <width>x<height>", value))?;
+
+                size = Size::new(width.parse()?, height.parse()?);
+
+                if size.width == 0 || size.height == 0 {
+                    return Err(format!("invalid size \"{}\", width and height can't be 0", value).into());
+                }
+            }
+            "--speeds" => {
+                speed_table = Some(PathBuf::from(args.next().ok_or("--speeds needs a path")?));
+            }
+            "--theme" => {
+                theme = Some(PathBuf::from(args.next().ok_or("--theme needs a path")?));
+            }
+            "--osc" => {
+                osc_port = Some(args.next().ok_or("--osc needs a port")?.parse()?);
+            }
+            "--osc-send" => {
+                osc_send_port = Some(args.next().ok_or("--osc-send needs a port")?.parse()?);
+            }
+            _ => return Err(format!("unknown argument \"{}\"", arg).into()),
+        }
+    }
+
+    Ok(Args {
+        screenshot: path.map(|path| Screenshot { path, size }),
+        speed_table,
+        osc_port,
+        osc_send_port,
+        theme,
+    })
+}
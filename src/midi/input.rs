@@ -0,0 +1,64 @@
+//! MIDI ports read through `midir`
+
+use std::error::Error;
+
+use midir::{MidiInput, MidiInputConnection};
+
+use super::{MidiEvent, MidiParser};
+
+/// Connects to every MIDI input port, and on Unix to a virtual port other
+/// software can send to
+///
+/// `on_event` runs on the MIDI threads, the inputs are read for as long as
+/// the connections live.
+pub fn connect_inputs<F>(on_event: F) -> Result<Vec<MidiInputConnection<MidiParser>>, Box<dyn Error>>
+where
+    F: Fn(MidiEvent) + Clone + Send + 'static,
+{
+    let client_name = env!("CARGO_PKG_NAME");
+    let mut connections = Vec::new();
+
+    for port in MidiInput::new(client_name)?.ports() {
+        let input = MidiInput::new(client_name)?;
+        let port_name = input.port_name(&port)?;
+        let on_event = on_event.clone();
+
+        let connection = input.connect(
+            &port,
+            &port_name,
+            move |_, bytes, parser: &mut MidiParser| {
+                if let Some(event) = parser.parse(bytes) {
+                    on_event(event);
+                }
+            },
+            MidiParser::new(),
+        );
+
+        match connection {
+            Ok(connection) => connections.push(connection),
+            Err(error) => eprintln!("{}: {}", port_name, error),
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use midir::os::unix::VirtualInput;
+
+        let connection = MidiInput::new(client_name)?.create_virtual(
+            client_name,
+            move |_, bytes, parser: &mut MidiParser| {
+                if let Some(event) = parser.parse(bytes) {
+                    on_event(event);
+                }
+            },
+            MidiParser::new(),
+        );
+
+        match connection {
+            Ok(connection) => connections.push(connection),
+            Err(error) => eprintln!("virtual MIDI port: {}", error),
+        }
+    }
+
+    Ok(connections)
+}
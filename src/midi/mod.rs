@@ -0,0 +1,244 @@
+//! MIDI control of the speed
+//!
+//! Raw bytes from an input are turned into [`MidiEvent`]s by a
+//! [`MidiParser`], which also assembles 14-bit NRPNs out of their control
+//! changes. A [`MidiMap`] then binds the [`Controller`] of an event to a
+//! [`MidiTarget`], either from its file or by MIDI learn.
+//!
+//! Hardware and virtual ports are read through `midir` when the `midi`
+//! feature is enabled, see [`connect_inputs`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::h_slider::normal::Normal;
+
+#[cfg(feature = "midi")]
+mod input;
+// only the ports and the tests send raw bytes
+#[cfg(any(feature = "midi", test))]
+mod parser;
+
+#[cfg(feature = "midi")]
+pub use input::connect_inputs;
+#[cfg(any(feature = "midi", test))]
+pub use parser::MidiParser;
+
+/// Version written to new mapping files, older ones are still read
+pub const MIDI_MAP_VERSION: u32 = 1;
+
+/// Largest value of a 14-bit NRPN or pitch bend
+const MAX_14_BIT: u16 = 0x3FFF;
+
+/// A controller moved, channels count from 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(any(feature = "midi", test)), allow(dead_code))]
+pub enum MidiEvent {
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    Nrpn { channel: u8, parameter: u16, value: u16 },
+    PitchBend { channel: u8, value: u16 },
+}
+
+impl MidiEvent {
+    pub fn controller(&self) -> Controller {
+        match *self {
+            MidiEvent::ControlChange { channel, controller, .. } => Controller::Cc { channel, controller },
+            MidiEvent::Nrpn { channel, parameter, .. } => Controller::Nrpn { channel, parameter },
+            MidiEvent::PitchBend { channel, .. } => Controller::PitchBend { channel },
+        }
+    }
+
+    /// The value over the whole range of the controller
+    pub fn normal(&self) -> Normal {
+        match *self {
+            MidiEvent::ControlChange { value, .. } => Normal::from_clipped(value as f32 / 127.),
+            MidiEvent::Nrpn { value, .. } | MidiEvent::PitchBend { value, .. } => {
+                Normal::from_clipped(value as f32 / MAX_14_BIT as f32)
+            }
+        }
+    }
+}
+
+/// Something on a MIDI device that sends values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Controller {
+    Cc { channel: u8, controller: u8 },
+    Nrpn { channel: u8, parameter: u16 },
+    PitchBend { channel: u8 },
+}
+
+/// A parameter of the controls a [`Controller`] can drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiTarget {
+    Speed,
+}
+
+/// Which controller drives which target, a target has one controller at most
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MidiMap {
+    mappings: BTreeMap<Controller, MidiTarget>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MidiMapFile {
+    version: u32,
+    #[serde(default)]
+    mappings: Vec<Mapping>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Mapping {
+    target: MidiTarget,
+    controller: Controller,
+}
+
+/// Read before the rest of the file, which depends on it
+#[derive(Deserialize)]
+struct MidiMapVersion {
+    version: u32,
+}
+
+impl MidiMap {
+    pub fn target(&self, controller: Controller) -> Option<MidiTarget> {
+        self.mappings.get(&controller).copied()
+    }
+
+    /// Binds `controller` to `target` in place of their previous bindings
+    pub fn bind(&mut self, controller: Controller, target: MidiTarget) {
+        self.mappings.retain(|_, other| *other != target);
+        self.mappings.insert(controller, target);
+    }
+
+    /// `midi.toml` in the user's config directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|config_dir| config_dir.join(env!("CARGO_PKG_NAME")).join("midi.toml"))
+    }
+
+    /// An empty map when there is no file yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MidiMapError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::from_toml(&contents),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(MidiMapError::Io(error)),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MidiMapError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(MidiMapError::Io)?;
+        }
+
+        fs::write(path, self.to_toml()?).map_err(MidiMapError::Io)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, MidiMapError> {
+        let MidiMapVersion { version } = toml::from_str(contents).map_err(MidiMapError::Toml)?;
+
+        if version > MIDI_MAP_VERSION {
+            return Err(MidiMapError::UnsupportedVersion(version));
+        }
+
+        let file: MidiMapFile = toml::from_str(contents).map_err(MidiMapError::Toml)?;
+        let mut map = Self::default();
+
+        for Mapping { target, controller } in file.mappings {
+            map.bind(controller, target);
+        }
+
+        Ok(map)
+    }
+
+    pub fn to_toml(&self) -> Result<String, MidiMapError> {
+        let file = MidiMapFile {
+            version: MIDI_MAP_VERSION,
+            mappings: self
+                .mappings
+                .iter()
+                .map(|(&controller, &target)| Mapping { target, controller })
+                .collect(),
+        };
+
+        toml::to_string(&file).map_err(MidiMapError::Serialize)
+    }
+}
+
+#[derive(Debug)]
+pub enum MidiMapError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// Written by a later version of the app
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for MidiMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiMapError::Io(error) => write!(f, "{}", error),
+            MidiMapError::Toml(error) => write!(f, "{}", error),
+            MidiMapError::Serialize(error) => write!(f, "{}", error),
+            MidiMapError::UnsupportedVersion(version) => write!(
+                f,
+                "MIDI mappings version {} is newer than {}",
+                version, MIDI_MAP_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MidiMapError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_target_has_one_controller() {
+        let mut map = MidiMap::default();
+        let modulation = Controller::Cc { channel: 0, controller: 1 };
+        let bend = Controller::PitchBend { channel: 0 };
+
+        map.bind(modulation, MidiTarget::Speed);
+        map.bind(bend, MidiTarget::Speed);
+
+        assert_eq!(map.target(modulation), None);
+        assert_eq!(map.target(bend), Some(MidiTarget::Speed));
+    }
+
+    #[test]
+    fn mappings_round_trip_through_toml() {
+        let mut map = MidiMap::default();
+        map.bind(Controller::Nrpn { channel: 2, parameter: 300 }, MidiTarget::Speed);
+
+        let toml = map.to_toml().unwrap();
+
+        assert!(toml.contains("type = \"nrpn\""));
+        assert_eq!(MidiMap::from_toml(&toml).unwrap(), map);
+        assert!(matches!(
+            MidiMap::from_toml("version = 9"),
+            Err(MidiMapError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn values_span_the_normal() {
+        let cc = |value| MidiEvent::ControlChange { channel: 0, controller: 1, value };
+        let bend = |value| MidiEvent::PitchBend { channel: 0, value };
+
+        assert_eq!(cc(0).normal(), Normal::MIN);
+        assert_eq!(cc(127).normal(), Normal::MAX);
+        assert_eq!(bend(MAX_14_BIT).normal(), Normal::MAX);
+        assert!((bend(0x2000).normal().as_f32() - 0.5).abs() < 1e-3);
+    }
+}
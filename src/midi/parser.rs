@@ -0,0 +1,171 @@
+//! Raw MIDI bytes to [`MidiEvent`]s
+
+use super::MidiEvent;
+
+const CONTROL_CHANGE: u8 = 0xB0;
+const PITCH_BEND: u8 = 0xE0;
+
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+
+/// Deselects the parameter, as an NRPN or an RPN
+const NULL_PARAMETER: u16 = 0x3FFF;
+
+/// The NRPN a channel is selecting and editing
+#[derive(Debug, Clone, Copy, Default)]
+struct NrpnState {
+    parameter_msb: Option<u8>,
+    parameter_lsb: Option<u8>,
+    value_msb: u8,
+}
+
+impl NrpnState {
+    fn parameter(&self) -> Option<u16> {
+        Some(((self.parameter_msb? as u16) << 7) | self.parameter_lsb? as u16)
+    }
+}
+
+/// Parses the messages of one input
+///
+/// NRPNs are sent as a run of control changes, the parser keeps track of
+/// them per channel and publishes their value on every data entry, the
+/// coarse one first. Selecting an RPN or the null parameter ends the NRPN,
+/// the data entries that follow aren't for it.
+#[derive(Debug, Clone, Default)]
+pub struct MidiParser {
+    nrpns: [NrpnState; 16],
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `None` for messages that aren't events, or only part of one
+    pub fn parse(&mut self, bytes: &[u8]) -> Option<MidiEvent> {
+        let (&status, data) = bytes.split_first()?;
+        let channel = status & 0x0F;
+
+        match (status & 0xF0, data) {
+            (CONTROL_CHANGE, &[controller, value, ..]) => {
+                self.control_change(channel, controller & 0x7F, value & 0x7F)
+            }
+            (PITCH_BEND, &[lsb, msb, ..]) => Some(MidiEvent::PitchBend {
+                channel,
+                value: (((msb & 0x7F) as u16) << 7) | (lsb & 0x7F) as u16,
+            }),
+            _ => None,
+        }
+    }
+
+    fn control_change(&mut self, channel: u8, controller: u8, value: u8) -> Option<MidiEvent> {
+        let nrpn = &mut self.nrpns[channel as usize];
+
+        match controller {
+            NRPN_MSB | NRPN_LSB => {
+                if controller == NRPN_MSB {
+                    nrpn.parameter_msb = Some(value);
+                } else {
+                    nrpn.parameter_lsb = Some(value);
+                }
+
+                if nrpn.parameter() == Some(NULL_PARAMETER) {
+                    *nrpn = NrpnState::default();
+                }
+
+                None
+            }
+            RPN_MSB | RPN_LSB => {
+                *nrpn = NrpnState::default();
+                None
+            }
+            DATA_ENTRY_MSB | DATA_ENTRY_LSB if nrpn.parameter().is_some() => {
+                let value = if controller == DATA_ENTRY_MSB {
+                    nrpn.value_msb = value;
+                    (value as u16) << 7
+                } else {
+                    ((nrpn.value_msb as u16) << 7) | value as u16
+                };
+
+                Some(MidiEvent::Nrpn {
+                    channel,
+                    parameter: nrpn.parameter()?,
+                    value,
+                })
+            }
+            _ => Some(MidiEvent::ControlChange {
+                channel,
+                controller,
+                value,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_control_changes_and_pitch_bends() {
+        let mut parser = MidiParser::new();
+
+        assert_eq!(
+            parser.parse(&[0xB3, 1, 64]),
+            Some(MidiEvent::ControlChange { channel: 3, controller: 1, value: 64 })
+        );
+        assert_eq!(
+            parser.parse(&[0xE0, 0x00, 0x40]),
+            Some(MidiEvent::PitchBend { channel: 0, value: 0x2000 })
+        );
+        assert_eq!(parser.parse(&[0x90, 60, 100]), None);
+        assert_eq!(parser.parse(&[0xB0, 1]), None);
+    }
+
+    #[test]
+    fn assembles_14_bit_nrpns() {
+        let mut parser = MidiParser::new();
+
+        assert_eq!(parser.parse(&[0xB1, NRPN_MSB, 2]), None);
+        assert_eq!(parser.parse(&[0xB1, NRPN_LSB, 44]), None);
+        assert_eq!(
+            parser.parse(&[0xB1, DATA_ENTRY_MSB, 0x40]),
+            Some(MidiEvent::Nrpn { channel: 1, parameter: 300, value: 0x2000 })
+        );
+        assert_eq!(
+            parser.parse(&[0xB1, DATA_ENTRY_LSB, 0x05]),
+            Some(MidiEvent::Nrpn { channel: 1, parameter: 300, value: 0x2005 })
+        );
+
+        // data entry on a channel without an NRPN is a plain control change
+        assert_eq!(
+            parser.parse(&[0xB0, DATA_ENTRY_MSB, 3]),
+            Some(MidiEvent::ControlChange { channel: 0, controller: DATA_ENTRY_MSB, value: 3 })
+        );
+    }
+
+    #[test]
+    fn rpns_and_the_null_parameter_end_the_nrpn() {
+        let mut parser = MidiParser::new();
+        let data_entry = |parser: &mut MidiParser| parser.parse(&[0xB2, DATA_ENTRY_MSB, 2]);
+
+        parser.parse(&[0xB2, NRPN_MSB, 0]);
+        parser.parse(&[0xB2, NRPN_LSB, 7]);
+        assert!(matches!(data_entry(&mut parser), Some(MidiEvent::Nrpn { parameter: 7, .. })));
+
+        // pitch bend sensitivity
+        assert_eq!(parser.parse(&[0xB2, RPN_MSB, 0]), None);
+        assert_eq!(parser.parse(&[0xB2, RPN_LSB, 0]), None);
+        assert!(matches!(data_entry(&mut parser), Some(MidiEvent::ControlChange { .. })));
+
+        parser.parse(&[0xB2, NRPN_MSB, 0]);
+        parser.parse(&[0xB2, NRPN_LSB, 7]);
+        assert_eq!(parser.parse(&[0xB2, NRPN_MSB, 127]), None);
+        assert_eq!(parser.parse(&[0xB2, NRPN_LSB, 127]), None);
+        assert!(matches!(data_entry(&mut parser), Some(MidiEvent::ControlChange { .. })));
+    }
+}
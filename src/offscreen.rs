@@ -0,0 +1,240 @@
+//! Renders a [`Program`] into an offscreen texture instead of a window
+//! surface, so screenshots and golden images can be produced on machines
+//! without a display.
+//!
+//! The adapter is picked the same way as in [`main`](crate::main), set
+//! `WGPU_ADAPTER_NAME` to select a software adapter such as lavapipe or
+//! llvmpipe. A fallback adapter is requested when no other one is found.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::theme::Theme;
+
+use iced_wgpu::graphics::Viewport;
+use iced_wgpu::{wgpu, Backend, Settings};
+
+use iced_winit::core::{clipboard, mouse, renderer, Color, Size};
+use iced_winit::futures;
+use iced_winit::runtime::{program, Debug, Program};
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// An RGBA8 image read back from the GPU
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+
+        Ok(())
+    }
+}
+
+pub struct Offscreen {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: iced_renderer::Renderer<Theme>,
+}
+
+impl Offscreen {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let backend = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: backend,
+            ..Default::default()
+        });
+
+        let (device, queue) = futures::futures::executor::block_on(async {
+            let adapter =
+                match wgpu::util::initialize_adapter_from_env_or_default(&instance, backend, None)
+                    .await
+                {
+                    Some(adapter) => Some(adapter),
+                    None => {
+                        instance
+                            .request_adapter(&wgpu::RequestAdapterOptions {
+                                force_fallback_adapter: true,
+                                ..Default::default()
+                            })
+                            .await
+                    }
+                }
+                .ok_or("No adapter found")?;
+
+            let adapter_features = adapter.features();
+
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        label: None,
+                        features: adapter_features & wgpu::Features::default(),
+                        limits: wgpu::Limits::downlevel_webgl2_defaults()
+                            .using_resolution(adapter.limits()),
+                    },
+                    None,
+                )
+                .await
+                .map_err(Box::<dyn Error>::from)
+        })?;
+
+        // This is the WGPU renderer
+        let wgpu_renderer =
+            iced_wgpu::Renderer::new(Backend::new(&device, &queue, Settings::default(), FORMAT));
+
+        Ok(Self {
+            device,
+            queue,
+            renderer: iced_renderer::Renderer::Wgpu(wgpu_renderer),
+        })
+    }
+
+    /// Builds `program`, draws it once at the given physical size and reads
+    /// the result back
+    pub fn render<P>(
+        &mut self,
+        program: P,
+        physical_size: Size<u32>,
+        scale_factor: f64,
+        cursor: mouse::Cursor,
+        theme: &Theme,
+        background_color: Color,
+    ) -> Result<Snapshot, Box<dyn Error>>
+    where
+        P: Program<Renderer = iced_renderer::Renderer<Theme>> + 'static,
+    {
+        let viewport = Viewport::with_physical_size(physical_size, scale_factor);
+        let mut debug = Debug::new();
+
+        let mut state = program::State::new(
+            program,
+            viewport.logical_size(),
+            &mut self.renderer,
+            &mut debug,
+        );
+
+        // An update with no queued events only lays out and draws the program
+        let _ = state.update(
+            viewport.logical_size(),
+            cursor,
+            &mut self.renderer,
+            theme,
+            &renderer::Style {
+                text_color: theme.palette().text,
+            },
+            &mut clipboard::Null,
+            &mut debug,
+        );
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen"),
+            size: wgpu::Extent3d {
+                width: physical_size.width,
+                height: physical_size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Rows of a texture copy must be aligned to COPY_BYTES_PER_ROW_ALIGNMENT
+        let unpadded_bytes_per_row = physical_size.width * 4;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen readback"),
+            size: (padded_bytes_per_row * physical_size.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        if let iced_renderer::Renderer::Wgpu(ref mut inner_renderer) = self.renderer {
+            let (device, queue) = (&self.device, &self.queue);
+
+            inner_renderer.with_primitives(|backend, primitive| {
+                backend.present(
+                    device,
+                    queue,
+                    &mut encoder,
+                    Some(background_color),
+                    &view,
+                    primitive,
+                    &viewport,
+                    &debug.overlay(),
+                );
+            });
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: physical_size.width,
+                height: physical_size.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        let pixels = buffer_slice
+            .get_mapped_range()
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+            .copied()
+            .collect();
+
+        buffer.unmap();
+
+        Ok(Snapshot {
+            width: physical_size.width,
+            height: physical_size.height,
+            pixels,
+        })
+    }
+}
@@ -0,0 +1,249 @@
+//! Remote control of the speed over OSC
+//!
+//! An [`OscReceiver`] listens on a UDP socket for [`OscCommand`]s:
+//!
+//! - `/speed <float>`, a speed, snapped to the table in quantized mode
+//! - `/speed/index <int>`, an entry of the speed table
+//! - `/speed/mode quantized|unquantized`
+//!
+//! An [`OscBroadcaster`] sends the same addresses back out whenever the
+//! state of the controls changes.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::thread::{self, JoinHandle};
+
+use crate::speed::SpeedMode;
+
+mod packet;
+
+pub use packet::{OscArg, OscMessage};
+
+/// Largest packet read, more than any command needs
+const MAX_PACKET_SIZE: usize = 1536;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OscCommand {
+    Speed(f32),
+    SpeedIndex(i64),
+    SpeedMode(SpeedMode),
+}
+
+impl OscCommand {
+    /// `None` for addresses or arguments that aren't a command
+    pub fn from_message(message: &OscMessage) -> Option<Self> {
+        let [arg] = message.args.as_slice() else {
+            return None;
+        };
+
+        match message.address.as_str() {
+            "/speed" => arg.as_f32().filter(|speed| speed.is_finite()).map(OscCommand::Speed),
+            "/speed/index" => arg.as_i64().map(OscCommand::SpeedIndex),
+            "/speed/mode" => match arg.as_str()? {
+                "quantized" => Some(OscCommand::SpeedMode(SpeedMode::Quantized)),
+                "unquantized" => Some(OscCommand::SpeedMode(SpeedMode::Unquantized)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Reads commands on a thread of its own
+pub struct OscReceiver {
+    local_addr: SocketAddr,
+    _thread: JoinHandle<()>,
+}
+
+impl OscReceiver {
+    /// Listens on `addr` and hands every command to `on_command`, packets
+    /// that aren't commands are skipped
+    ///
+    /// The thread stops on the first error it can't read past, a reset
+    /// connection only means a peer went away.
+    pub fn spawn<F>(addr: impl ToSocketAddrs, on_command: F) -> io::Result<Self>
+    where
+        F: Fn(OscCommand) + Send + 'static,
+    {
+        let socket = UdpSocket::bind(addr)?;
+        let local_addr = socket.local_addr()?;
+
+        let thread = thread::Builder::new()
+            .name("osc receiver".to_owned())
+            .spawn(move || {
+                let mut buffer = [0; MAX_PACKET_SIZE];
+
+                loop {
+                    let size = match socket.recv(&mut buffer) {
+                        Ok(size) => size,
+                        // windows reports an ICMP port unreachable as a reset
+                        // connection on the next recv
+                        Err(error)
+                            if matches!(
+                                error.kind(),
+                                io::ErrorKind::ConnectionReset | io::ErrorKind::Interrupted
+                            ) =>
+                        {
+                            continue
+                        }
+                        Err(error) => {
+                            eprintln!("OSC: {}, no longer receiving", error);
+                            return;
+                        }
+                    };
+
+                    match OscMessage::decode(&buffer[..size]) {
+                        Ok(messages) => messages
+                            .iter()
+                            .filter_map(OscCommand::from_message)
+                            .for_each(&on_command),
+                        Err(error) => eprintln!("OSC: {}", error),
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            local_addr,
+            _thread: thread,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+/// What the broadcaster reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OscState {
+    pub speed: f32,
+    /// The entry of the speed table, for quantized speeds
+    pub index: Option<usize>,
+    pub speed_mode: SpeedMode,
+}
+
+/// Sends the state of the controls to a peer as it changes
+#[derive(Debug)]
+pub struct OscBroadcaster {
+    socket: UdpSocket,
+    target: SocketAddr,
+    last: Option<OscState>,
+}
+
+impl OscBroadcaster {
+    pub fn new(target: SocketAddr) -> io::Result<Self> {
+        let any_port: SocketAddr = if target.is_ipv4() {
+            ([127, 0, 0, 1], 0).into()
+        } else {
+            (std::net::Ipv6Addr::LOCALHOST, 0).into()
+        };
+
+        Ok(Self {
+            socket: UdpSocket::bind(any_port)?,
+            target,
+            last: None,
+        })
+    }
+
+    /// Sends the parts of `state` that changed since the last broadcast, all
+    /// of it the first time
+    ///
+    /// A failed send leaves the last broadcast as it was, so everything that
+    /// changed since is sent again next time.
+    pub fn broadcast(&mut self, state: OscState) -> io::Result<()> {
+        let last = self.last;
+
+        if last.is_none_or(|last| last.speed_mode != state.speed_mode) {
+            let mode = match state.speed_mode {
+                SpeedMode::Quantized => "quantized",
+                SpeedMode::Unquantized => "unquantized",
+            };
+            self.send(OscMessage::new("/speed/mode", vec![OscArg::String(mode.to_owned())]))?;
+        }
+
+        if last.is_none_or(|last| last.speed != state.speed) {
+            self.send(OscMessage::new("/speed", vec![OscArg::Float(state.speed)]))?;
+        }
+
+        if let Some(index) = state.index {
+            if last.is_none_or(|last| last.index != state.index) {
+                self.send(OscMessage::new("/speed/index", vec![OscArg::Int(index as i32)]))?;
+            }
+        }
+
+        self.last = Some(state);
+
+        Ok(())
+    }
+
+    fn send(&self, message: OscMessage) -> io::Result<()> {
+        self.socket.send_to(&message.encode(), self.target).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn parses_commands() {
+        let command = |address: &str, arg| OscCommand::from_message(&OscMessage::new(address, vec![arg]));
+
+        assert_eq!(command("/speed", OscArg::Float(1.5)), Some(OscCommand::Speed(1.5)));
+        assert_eq!(command("/speed", OscArg::Int(2)), Some(OscCommand::Speed(2.)));
+        assert_eq!(command("/speed/index", OscArg::Int(3)), Some(OscCommand::SpeedIndex(3)));
+        assert_eq!(
+            command("/speed/mode", OscArg::String("unquantized".to_owned())),
+            Some(OscCommand::SpeedMode(SpeedMode::Unquantized))
+        );
+        assert_eq!(command("/speed/mode", OscArg::String("fast".to_owned())), None);
+        assert_eq!(command("/speed", OscArg::Float(f32::NAN)), None);
+        assert_eq!(command("/volume", OscArg::Float(1.)), None);
+    }
+
+    #[test]
+    fn receives_commands_over_loopback() {
+        let (sender, receiver) = mpsc::channel();
+        let osc = OscReceiver::spawn("127.0.0.1:0", move |command| sender.send(command).unwrap()).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for message in [
+            OscMessage::new("/nothing", vec![]),
+            OscMessage::new("/speed/index", vec![OscArg::Int(2)]),
+        ] {
+            client.send_to(&message.encode(), osc.local_addr()).unwrap();
+        }
+
+        assert_eq!(receiver.recv_timeout(TIMEOUT), Ok(OscCommand::SpeedIndex(2)));
+    }
+
+    #[test]
+    fn broadcasts_changes_only() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let mut broadcaster = OscBroadcaster::new(peer.local_addr().unwrap()).unwrap();
+
+        let receive = || {
+            let mut buffer = [0; MAX_PACKET_SIZE];
+            let size = peer.recv(&mut buffer).unwrap();
+            OscMessage::decode(&buffer[..size]).unwrap().remove(0)
+        };
+
+        let state = OscState { speed: 1., index: Some(3), speed_mode: SpeedMode::Quantized };
+        broadcaster.broadcast(state).unwrap();
+
+        assert_eq!(receive().address, "/speed/mode");
+        assert_eq!(receive().address, "/speed");
+        assert_eq!(receive().address, "/speed/index");
+
+        broadcaster.broadcast(state).unwrap();
+        broadcaster.broadcast(OscState { speed: 1.2, index: None, ..state }).unwrap();
+
+        assert_eq!(receive(), OscMessage::new("/speed", vec![OscArg::Float(1.2)]));
+    }
+}
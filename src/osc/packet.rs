@@ -0,0 +1,264 @@
+//! OSC 1.0 messages and bundles, as they travel over UDP
+
+use std::fmt;
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+}
+
+impl OscArg {
+    /// Any number as a float
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            OscArg::Int(value) => Some(value as f32),
+            OscArg::Long(value) => Some(value as f32),
+            OscArg::Float(value) => Some(value),
+            OscArg::Double(value) => Some(value as f32),
+            OscArg::String(_) => None,
+        }
+    }
+
+    /// Integers, and floats without a fractional part
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            OscArg::Int(value) => Some(value as i64),
+            OscArg::Long(value) => Some(value),
+            OscArg::Float(value) if value.fract() == 0.0 => Some(value as i64),
+            OscArg::Double(value) if value.fract() == 0.0 => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OscArg::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self {
+            address: address.into(),
+            args,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let type_tags: String = std::iter::once(',')
+            .chain(self.args.iter().map(|arg| match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Long(_) => 'h',
+                OscArg::Float(_) => 'f',
+                OscArg::Double(_) => 'd',
+                OscArg::String(_) => 's',
+            }))
+            .collect();
+
+        write_string(&mut bytes, &self.address);
+        write_string(&mut bytes, &type_tags);
+
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => bytes.extend(value.to_be_bytes()),
+                OscArg::Long(value) => bytes.extend(value.to_be_bytes()),
+                OscArg::Float(value) => bytes.extend(value.to_be_bytes()),
+                OscArg::Double(value) => bytes.extend(value.to_be_bytes()),
+                OscArg::String(value) => write_string(&mut bytes, value),
+            }
+        }
+
+        bytes
+    }
+
+    /// The messages of a packet, those of a bundle in order
+    pub fn decode(bytes: &[u8]) -> Result<Vec<OscMessage>, OscError> {
+        let mut messages = Vec::new();
+        decode_packet(bytes, &mut messages)?;
+        Ok(messages)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OscError {
+    /// The packet ends in the middle of something
+    Truncated,
+    /// Addresses start with a slash, type tags with a comma
+    Malformed,
+    UnknownType(char),
+}
+
+impl fmt::Display for OscError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OscError::Truncated => write!(f, "truncated OSC packet"),
+            OscError::Malformed => write!(f, "malformed OSC packet"),
+            OscError::UnknownType(tag) => write!(f, "unsupported OSC type tag '{}'", tag),
+        }
+    }
+}
+
+impl std::error::Error for OscError {}
+
+fn decode_packet(bytes: &[u8], messages: &mut Vec<OscMessage>) -> Result<(), OscError> {
+    let mut reader = Reader { bytes };
+
+    if bytes.starts_with(BUNDLE_TAG) {
+        reader.take(BUNDLE_TAG.len())?;
+        // the time tag, everything is handled on arrival
+        reader.take(8)?;
+
+        while !reader.bytes.is_empty() {
+            let size = i32::from_be_bytes(reader.array()?);
+            let size = usize::try_from(size).map_err(|_| OscError::Malformed)?;
+            decode_packet(reader.take(size)?, messages)?;
+        }
+
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(OscError::Malformed);
+    }
+
+    // some old senders leave out the type tags of messages without arguments
+    let type_tags = if reader.bytes.is_empty() {
+        String::from(",")
+    } else {
+        reader.string()?
+    };
+    let type_tags = type_tags.strip_prefix(',').ok_or(OscError::Malformed)?;
+
+    let args = type_tags
+        .chars()
+        .map(|tag| {
+            Ok(match tag {
+                'i' => OscArg::Int(i32::from_be_bytes(reader.array()?)),
+                'h' => OscArg::Long(i64::from_be_bytes(reader.array()?)),
+                'f' => OscArg::Float(f32::from_be_bytes(reader.array()?)),
+                'd' => OscArg::Double(f64::from_be_bytes(reader.array()?)),
+                's' => OscArg::String(reader.string()?),
+                tag => return Err(OscError::UnknownType(tag)),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    messages.push(OscMessage { address, args });
+
+    Ok(())
+}
+
+/// Null terminated, then padded to a multiple of 4 bytes
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend(string.as_bytes());
+    bytes.push(0);
+
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], OscError> {
+        if count > self.bytes.len() {
+            return Err(OscError::Truncated);
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], OscError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn string(&mut self) -> Result<String, OscError> {
+        let length = self
+            .bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(OscError::Truncated)?;
+        let padded_length = (length + 4) / 4 * 4;
+
+        let bytes = self.take(padded_length)?;
+        String::from_utf8(bytes[..length].to_vec()).map_err(|_| OscError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_padded_strings_and_big_endian_numbers() {
+        let message = OscMessage::new("/speed", vec![OscArg::Float(1.5)]);
+
+        assert_eq!(
+            message.encode(),
+            [b"/speed\0\0".as_slice(), b",f\0\0", &1.5f32.to_be_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn round_trips_every_type() {
+        let message = OscMessage::new(
+            "/speed/mode",
+            vec![
+                OscArg::Int(-3),
+                OscArg::Long(1 << 40),
+                OscArg::Float(0.25),
+                OscArg::Double(2.5),
+                OscArg::String("quantized".to_owned()),
+            ],
+        );
+
+        assert_eq!(OscMessage::decode(&message.encode()), Ok(vec![message]));
+    }
+
+    #[test]
+    fn flattens_bundles() {
+        let first = OscMessage::new("/speed", vec![OscArg::Float(2.)]).encode();
+        let second = OscMessage::new("/speed/index", vec![OscArg::Int(4)]).encode();
+
+        let mut bundle = [BUNDLE_TAG, &[0; 7], &[1]].concat();
+        for message in [&first, &second] {
+            bundle.extend((message.len() as i32).to_be_bytes());
+            bundle.extend(message);
+        }
+
+        let messages = OscMessage::decode(&bundle).unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].args, vec![OscArg::Int(4)]);
+    }
+
+    #[test]
+    fn rejects_broken_packets() {
+        let message = OscMessage::new("/speed", vec![OscArg::Float(2.)]).encode();
+
+        assert_eq!(OscMessage::decode(&message[..10]), Err(OscError::Truncated));
+        assert_eq!(OscMessage::decode(b"speed\0\0\0,\0\0\0"), Err(OscError::Malformed));
+        assert_eq!(OscMessage::decode(b"/speed\0\0,x\0\0"), Err(OscError::UnknownType('x')));
+    }
+}
@@ -0,0 +1,374 @@
+//! Named snapshots of the controls
+//!
+//! A [`Preset`] holds every parameter of the controls and is stored as a TOML
+//! file named after it in a [`PresetStore`]. Two presets can be morphed into
+//! one another, each parameter moving through its [`Normal`] space: speeds
+//! through their [`SpeedRange`], an octave at a time.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::h_slider::normal::Normal;
+use crate::h_slider::taper::TaperedRange;
+use crate::session::SavedSpeed;
+use crate::speed::{SpeedMode, SpeedRange, SpeedTable};
+
+/// Version written to new preset files, older ones are still read
+pub const PRESET_VERSION: u32 = 1;
+
+const EXTENSION: &str = "toml";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preset {
+    pub speed_mode: SpeedMode,
+    pub speed: SavedSpeed,
+    pub loop_region: (Normal, Normal),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+    version: u32,
+    speed_mode: SpeedMode,
+    loop_start: f32,
+    loop_end: f32,
+    speed: SavedSpeed,
+}
+
+/// Read before the rest of the file, which depends on it
+#[derive(Deserialize)]
+struct PresetVersion {
+    version: u32,
+}
+
+impl Preset {
+    pub fn from_toml(contents: &str) -> Result<Self, PresetError> {
+        let PresetVersion { version } = toml::from_str(contents).map_err(PresetError::Toml)?;
+
+        if version > PRESET_VERSION {
+            return Err(PresetError::UnsupportedVersion(version));
+        }
+
+        let file: PresetFile = toml::from_str(contents).map_err(PresetError::Toml)?;
+
+        Ok(Self {
+            speed_mode: file.speed_mode,
+            speed: file.speed,
+            loop_region: (
+                Normal::from_clipped(file.loop_start),
+                Normal::from_clipped(file.loop_end),
+            ),
+        })
+    }
+
+    pub fn to_toml(self) -> Result<String, PresetError> {
+        let file = PresetFile {
+            version: PRESET_VERSION,
+            speed_mode: self.speed_mode,
+            loop_start: self.loop_region.0.as_f32(),
+            loop_end: self.loop_region.1.as_f32(),
+            speed: self.speed,
+        };
+
+        toml::to_string(&file).map_err(PresetError::Serialize)
+    }
+
+    /// This preset at `amount` 0, `other` at 1
+    ///
+    /// Speeds in between are equally spaced in octaves. The mode can't be in
+    /// between and flips half way, a quantized one snaps the speed to the
+    /// closest entry of `speed_table`.
+    pub fn morph(
+        &self,
+        other: &Preset,
+        amount: Normal,
+        speed_table: &SpeedTable,
+        speed_range: &SpeedRange,
+    ) -> Preset {
+        if amount == Normal::MIN {
+            return *self;
+        } else if amount == Normal::MAX {
+            return *other;
+        }
+
+        let lerp = |from: Normal, to: Normal| {
+            Normal::from_clipped(from.as_f32() + (to.as_f32() - from.as_f32()) * amount.as_f32())
+        };
+
+        let speed_mode = if amount.as_f32() < 0.5 { self.speed_mode } else { other.speed_mode };
+
+        let speed = if self.speed == other.speed {
+            self.speed
+        } else {
+            let from = speed_range.map_to_normal(self.speed.as_f32());
+            let to = speed_range.map_to_normal(other.speed.as_f32());
+            let normal = lerp(from, to);
+
+            match speed_mode {
+                SpeedMode::Quantized => {
+                    let index = speed_table.closest_index(normal, speed_range);
+
+                    SavedSpeed::Quantized(speed_table.speeds()[index].ratio)
+                }
+                SpeedMode::Unquantized => SavedSpeed::Unquantized(speed_range.unmap_to_value(normal)),
+            }
+        };
+
+        Preset {
+            speed_mode,
+            speed,
+            loop_region: (
+                lerp(self.loop_region.0, other.loop_region.0),
+                lerp(self.loop_region.1, other.loop_region.1),
+            ),
+        }
+    }
+}
+
+/// A directory of preset files
+#[derive(Debug, Clone)]
+pub struct PresetStore {
+    dir: PathBuf,
+}
+
+impl PresetStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `presets` in the user's config directory, if there is one
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|config_dir| config_dir.join(env!("CARGO_PKG_NAME")).join("presets"))
+    }
+
+    /// Names of the stored presets in alphabetical order, none when the
+    /// directory doesn't exist yet
+    pub fn names(&self) -> Result<Vec<String>, PresetError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(PresetError::Io(error)),
+        };
+
+        let mut names = Vec::new();
+
+        for entry in entries {
+            let path = entry.map_err(PresetError::Io)?.path();
+
+            if path.extension().and_then(|extension| extension.to_str()) == Some(EXTENSION) {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+
+        names.sort_by_key(|name| name.to_lowercase());
+
+        Ok(names)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Preset, PresetError> {
+        let path = self.existing_path(name)?;
+        let contents = fs::read_to_string(path).map_err(PresetError::Io)?;
+
+        Preset::from_toml(&contents)
+    }
+
+    /// Replaces any preset with the same name
+    pub fn save(&self, name: &str, preset: &Preset) -> Result<(), PresetError> {
+        let path = self.path(name)?;
+
+        fs::create_dir_all(&self.dir).map_err(PresetError::Io)?;
+        fs::write(path, preset.to_toml()?).map_err(PresetError::Io)
+    }
+
+    /// Fails rather than replacing another preset
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), PresetError> {
+        let from_path = self.existing_path(from)?;
+        let to_path = self.path(to)?;
+
+        if to_path.exists() {
+            return Err(PresetError::AlreadyExists(to.to_owned()));
+        }
+
+        fs::rename(from_path, to_path).map_err(PresetError::Io)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), PresetError> {
+        fs::remove_file(self.existing_path(name)?).map_err(PresetError::Io)
+    }
+
+    /// The file of a preset, names that aren't plain file names are invalid
+    fn path(&self, name: &str) -> Result<PathBuf, PresetError> {
+        let is_valid = !name.trim().is_empty()
+            && name.trim() == name
+            && !name.starts_with('.')
+            && !name.contains(['/', '\\'])
+            && Path::new(name).file_name().is_some_and(|file_name| file_name == name);
+
+        if is_valid {
+            Ok(self.dir.join(format!("{}.{}", name, EXTENSION)))
+        } else {
+            Err(PresetError::InvalidName(name.to_owned()))
+        }
+    }
+
+    fn existing_path(&self, name: &str) -> Result<PathBuf, PresetError> {
+        let path = self.path(name)?;
+
+        if path.is_file() {
+            Ok(path)
+        } else {
+            Err(PresetError::NotFound(name.to_owned()))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// Written by a later version of the app
+    UnsupportedVersion(u32),
+    InvalidName(String),
+    AlreadyExists(String),
+    NotFound(String),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::Io(error) => write!(f, "{}", error),
+            PresetError::Toml(error) => write!(f, "{}", error),
+            PresetError::Serialize(error) => write!(f, "{}", error),
+            PresetError::UnsupportedVersion(version) => write!(
+                f,
+                "preset version {} is newer than {}",
+                version, PRESET_VERSION
+            ),
+            PresetError::InvalidName(name) => write!(f, "\"{}\" is not a valid preset name", name),
+            PresetError::AlreadyExists(name) => write!(f, "a preset named \"{}\" already exists", name),
+            PresetError::NotFound(name) => write!(f, "no preset named \"{}\"", name),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speed::Ratio;
+
+    fn preset(speed: SavedSpeed, loop_start: f32, loop_end: f32) -> Preset {
+        Preset {
+            speed_mode: SpeedMode::Quantized,
+            speed,
+            loop_region: (Normal::new(loop_start), Normal::new(loop_end)),
+        }
+    }
+
+    fn temp_store(name: &str) -> PresetStore {
+        let dir = std::env::temp_dir().join(format!("presets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        PresetStore::new(dir)
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let preset = preset(SavedSpeed::Quantized(Ratio::new(2, 3)), 0.25, 0.5);
+
+        assert_eq!(Preset::from_toml(&preset.to_toml().unwrap()).unwrap(), preset);
+    }
+
+    #[test]
+    fn stores_renames_and_deletes() {
+        let store = temp_store("store");
+        let slow = preset(SavedSpeed::Quantized(Ratio::new(1, 2)), 0., 1.);
+
+        assert_eq!(store.names().unwrap(), Vec::<String>::new());
+
+        store.save("slow", &slow).unwrap();
+        store.save("Fast", &preset(SavedSpeed::Unquantized(3.5), 0.1, 0.2)).unwrap();
+
+        assert_eq!(store.names().unwrap(), vec!["Fast", "slow"]);
+        assert_eq!(store.load("slow").unwrap(), slow);
+
+        assert!(matches!(store.rename("slow", "Fast"), Err(PresetError::AlreadyExists(_))));
+        store.rename("slow", "Half speed").unwrap();
+        assert_eq!(store.load("Half speed").unwrap(), slow);
+
+        store.delete("Fast").unwrap();
+        assert_eq!(store.names().unwrap(), vec!["Half speed"]);
+        assert!(matches!(store.load("Fast"), Err(PresetError::NotFound(_))));
+
+        fs::remove_dir_all(&store.dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_file_names() {
+        let store = temp_store("names");
+        let preset = preset(SavedSpeed::Unquantized(1.), 0., 1.);
+
+        for name in ["", " ", "../escape", "a/b", ".hidden", " padded "] {
+            assert!(
+                matches!(store.save(name, &preset), Err(PresetError::InvalidName(_))),
+                "{:?} was accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn morphs_speed_in_octaves() {
+        let table = SpeedTable::default();
+        let range = SpeedRange::default();
+        let from = preset(SavedSpeed::Quantized(Ratio::new(1, 1)), 0., 0.5);
+        let to = Preset {
+            speed_mode: SpeedMode::Unquantized,
+            ..preset(SavedSpeed::Quantized(Ratio::new(4, 1)), 0.5, 1.)
+        };
+
+        let half_way = from.morph(&to, Normal::CENTER, &table, &range);
+
+        assert!((half_way.speed.as_f32() - 2.).abs() < 1e-4);
+        assert_eq!(half_way.loop_region, (Normal::new(0.25), Normal::new(0.75)));
+        assert_eq!(half_way.speed_mode, SpeedMode::Unquantized);
+
+        assert_eq!(from.morph(&to, Normal::MIN, &table, &range), from);
+        assert_eq!(from.morph(&to, Normal::MAX, &table, &range), to);
+
+        // still quantized, the speed snaps to the table
+        let quarter_way = from.morph(&to, Normal::new(0.25), &table, &range);
+        assert_eq!(quarter_way.speed_mode, SpeedMode::Quantized);
+        assert!(matches!(quarter_way.speed, SavedSpeed::Quantized(_)));
+    }
+
+    #[test]
+    fn quantized_morphs_stay_in_the_table() {
+        let table = SpeedTable::default();
+        let range = SpeedRange::default();
+        let from = preset(SavedSpeed::Quantized(Ratio::new(1, 2)), 0., 1.);
+        let to = preset(SavedSpeed::Quantized(Ratio::new(8, 1)), 0., 1.);
+
+        for amount in [0.1, 0.3, 0.5, 0.7, 0.9] {
+            let morphed = from.morph(&to, Normal::new(amount), &table, &range);
+
+            assert!(
+                matches!(morphed.speed, SavedSpeed::Quantized(ratio) if table.speeds().iter().any(|speed| speed.ratio == ratio)),
+                "{:?} at {}",
+                morphed.speed,
+                amount
+            );
+        }
+
+        let half_way = from.morph(&to, Normal::CENTER, &table, &range);
+        assert_eq!(half_way.speed, SavedSpeed::Quantized(Ratio::new(2, 1)));
+    }
+}
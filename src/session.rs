@@ -0,0 +1,319 @@
+//! The state of the controls, kept from one run to the next
+//!
+//! A [`Session`] is saved as TOML in the user's config directory. Quantized
+//! speeds are stored by ratio rather than by index, so they land on the same
+//! speed when the speed table changes, or on the closest one when it's gone.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::speed::{Ratio, SpeedMode, SpeedRange, SpeedTable, SpeedValue};
+
+/// Version written to new session files, older ones are still read
+pub const SESSION_VERSION: u32 = 1;
+
+/// Quiet time after a change before the session is saved
+pub const SAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Session {
+    pub speed_mode: SpeedMode,
+    pub speed: SavedSpeed,
+}
+
+/// A [`SpeedValue`] independent of any speed table
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedSpeed {
+    Quantized(Ratio),
+    Unquantized(f32),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionFile {
+    version: u32,
+    speed_mode: SpeedMode,
+    speed: SavedSpeed,
+}
+
+/// Read before the rest of the file, which depends on it
+#[derive(Deserialize)]
+struct SessionVersion {
+    version: u32,
+}
+
+impl Session {
+    pub fn new(speed_mode: SpeedMode, speed: SpeedValue) -> Self {
+        Self {
+            speed_mode,
+            speed: speed.into(),
+        }
+    }
+
+    /// `session.toml` in the user's config directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir()
+            .map(|config_dir| config_dir.join(env!("CARGO_PKG_NAME")).join("session.toml"))
+    }
+
+    /// `Ok(None)` when there is no session file yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, SessionError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::from_toml(&contents).map(Some),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(SessionError::Io(error)),
+        }
+    }
+
+    /// Writes to a temporary file first so that a crash never leaves half a
+    /// session behind
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SessionError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(SessionError::Io)?;
+        }
+
+        let temporary_path = path.with_extension("toml.tmp");
+        fs::write(&temporary_path, self.to_toml()?).map_err(SessionError::Io)?;
+        fs::rename(&temporary_path, path).map_err(SessionError::Io)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, SessionError> {
+        let SessionVersion { version } = toml::from_str(contents).map_err(SessionError::Toml)?;
+
+        if version > SESSION_VERSION {
+            return Err(SessionError::UnsupportedVersion(version));
+        }
+
+        let file: SessionFile = toml::from_str(contents).map_err(SessionError::Toml)?;
+
+        Ok(Self {
+            speed_mode: file.speed_mode,
+            speed: file.speed,
+        })
+    }
+
+    pub fn to_toml(self) -> Result<String, SessionError> {
+        let file = SessionFile {
+            version: SESSION_VERSION,
+            speed_mode: self.speed_mode,
+            speed: self.speed,
+        };
+
+        toml::to_string(&file).map_err(SessionError::Serialize)
+    }
+
+    /// The saved speed in `speed_table`, see [`SavedSpeed::speed_value`]
+    pub fn speed_value(&self, speed_table: &SpeedTable, speed_range: &SpeedRange) -> SpeedValue {
+        self.speed.speed_value(speed_table, speed_range)
+    }
+}
+
+impl SavedSpeed {
+    pub fn as_f32(self) -> f32 {
+        match self {
+            SavedSpeed::Quantized(ratio) => ratio.as_f32(),
+            SavedSpeed::Unquantized(value) => value,
+        }
+    }
+
+    /// The speed in `speed_table`, a quantized speed missing from it becomes
+    /// the closest entry
+    pub fn speed_value(self, speed_table: &SpeedTable, speed_range: &SpeedRange) -> SpeedValue {
+        match self {
+            SavedSpeed::Quantized(ratio) => {
+                let distance = |other: Ratio| (other.as_f32().log2() - ratio.as_f32().log2()).abs();
+
+                let index = speed_table
+                    .speeds()
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| distance(a.ratio).total_cmp(&distance(b.ratio)))
+                    .map(|(index, _)| index)
+                    .unwrap_or(speed_table.default_index());
+
+                speed_table.quantized(index).unwrap()
+            }
+            SavedSpeed::Unquantized(value) => SpeedValue::Unquantized(speed_range.constrain(value)),
+        }
+    }
+}
+
+impl From<SpeedValue> for SavedSpeed {
+    fn from(speed: SpeedValue) -> Self {
+        match speed {
+            SpeedValue::Quantized { ratio, .. } => SavedSpeed::Quantized(ratio),
+            SpeedValue::Unquantized(value) => SavedSpeed::Unquantized(value),
+        }
+    }
+}
+
+/// Saves a session once it stopped changing for [`SAVE_DEBOUNCE`]
+#[derive(Debug)]
+pub struct SessionSaver {
+    path: PathBuf,
+    saved: Session,
+    /// A changed session and when to save it
+    pending: Option<(Session, Instant)>,
+}
+
+impl SessionSaver {
+    /// A saver for `path`, which holds `session` already
+    pub fn new(path: PathBuf, session: Session) -> Self {
+        Self {
+            path,
+            saved: session,
+            pending: None,
+        }
+    }
+
+    /// Takes note of the current session, every change pushes the save back
+    pub fn observe(&mut self, session: Session, now: Instant) {
+        let latest = self.pending.map_or(self.saved, |(pending, _)| pending);
+
+        if session != latest {
+            self.pending = (session != self.saved).then_some((session, now + SAVE_DEBOUNCE));
+        }
+    }
+
+    /// When the pending session is due
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.map(|(_, deadline)| deadline)
+    }
+
+    pub fn save_if_due(&mut self, now: Instant) -> Result<(), SessionError> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Saves the pending session at once, on exit
+    pub fn flush(&mut self) -> Result<(), SessionError> {
+        if let Some((session, _)) = self.pending.take() {
+            session.save(&self.path)?;
+            self.saved = session;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Serialize(toml::ser::Error),
+    /// Written by a later version of the app
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(error) => write!(f, "{}", error),
+            SessionError::Toml(error) => write!(f, "{}", error),
+            SessionError::Serialize(error) => write!(f, "{}", error),
+            SessionError::UnsupportedVersion(version) => write!(
+                f,
+                "session version {} is newer than {}",
+                version, SESSION_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speed::Division;
+
+    fn quantized(numerator: u32, denominator: u32) -> Session {
+        Session {
+            speed_mode: SpeedMode::Quantized,
+            speed: SavedSpeed::Quantized(Ratio::new(numerator, denominator)),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let session = quantized(3, 2);
+        let toml = session.to_toml().unwrap();
+
+        assert!(toml.contains("version = 1"));
+        assert!(toml.contains("quantized = \"3/2\""));
+        assert_eq!(Session::from_toml(&toml).unwrap(), session);
+
+        let session = Session {
+            speed_mode: SpeedMode::Unquantized,
+            speed: SavedSpeed::Unquantized(1.37),
+        };
+        assert_eq!(Session::from_toml(&session.to_toml().unwrap()).unwrap(), session);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let toml = "version = 2\nspeed_mode = \"quantized\"\nwhatever = true\n";
+
+        assert!(matches!(
+            Session::from_toml(toml),
+            Err(SessionError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn quantized_speeds_follow_their_ratio() {
+        let range = SpeedRange::default();
+        let full = SpeedTable::default();
+        let straight = SpeedTable::musical(&[Division::Straight]);
+
+        assert!(matches!(
+            quantized(3, 2).speed_value(&full, &range),
+            SpeedValue::Quantized { index, ratio } if full.speeds()[index].ratio == ratio
+                && ratio == Ratio::new(3, 2)
+        ));
+
+        // 3/2 isn't a straight speed, 2 is the closest
+        assert!(matches!(
+            quantized(3, 2).speed_value(&straight, &range),
+            SpeedValue::Quantized { ratio, .. } if ratio == Ratio::new(2, 1)
+        ));
+    }
+
+    #[test]
+    fn saves_after_changes_settle() {
+        let path = std::env::temp_dir()
+            .join(format!("session-{}", std::process::id()))
+            .join("session.toml");
+        let start = Instant::now();
+
+        let mut saver = SessionSaver::new(path.clone(), quantized(1, 1));
+
+        saver.observe(quantized(2, 1), start);
+        saver.observe(quantized(4, 1), start + SAVE_DEBOUNCE / 2);
+        saver.save_if_due(start + SAVE_DEBOUNCE).unwrap();
+
+        assert_eq!(Session::load(&path).unwrap(), None);
+
+        saver.save_if_due(start + SAVE_DEBOUNCE * 2).unwrap();
+
+        assert_eq!(Session::load(&path).unwrap(), Some(quantized(4, 1)));
+        assert_eq!(saver.deadline(), None);
+
+        // going back to the saved state has nothing left to save
+        saver.observe(quantized(2, 1), start);
+        saver.observe(quantized(4, 1), start);
+        assert_eq!(saver.deadline(), None);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::h_slider::normal::{Normal, NormalParam};
+use crate::h_slider::taper::TaperedRange;
+
+mod ratio;
+pub mod smoothing;
+mod table;
+pub mod transport;
+
+pub use ratio::{gcd, ParseRatioError, Ratio};
+
+pub use table::{Division, EntryProblem, MarkWeight, QuantizedSpeedValue, SpeedTable, SpeedTableError};
+
+/// Slowest speed a [`SpeedRange`] or a [`SpeedTable`] can reach
+pub const MIN_SPEED: f32 = 0.125;
+/// Fastest speed a [`SpeedRange`] or a [`SpeedTable`] can reach
+pub const MAX_SPEED: f32 = 8.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedMode {
+    Quantized,
+    Unquantized
+}
+
+/// A speed, either an entry of a [`SpeedTable`] or a free value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedValue {
+    /// The entry at `index` in the table, and its exact `ratio`
+    Quantized { index: usize, ratio: Ratio },
+    Unquantized(f32)
+}
+
+impl SpeedValue {
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            SpeedValue::Quantized { ratio, .. } => ratio.as_f32(),
+            SpeedValue::Unquantized(value) => *value,
+        }
+    }
+}
+
+/// A [`TaperedRange`] that defines a continuous log2 range of `f32` speed
+/// values, between 1/8 and 8 at most
+///
+/// Equal normal steps are equal ratios of speed, the range doesn't need to be
+/// centered around 1.
+#[derive(Debug, Copy, Clone)]
+pub struct SpeedRange {
+    min: f32,
+    max: f32,
+    min_octave: f32,
+    octave_span: f32,
+    octave_span_recip: f32,
+}
+
+impl SpeedRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        let min = min.max(MIN_SPEED);
+        let max = max.min(MAX_SPEED);
+
+        assert!(max > min);
+
+        let min_octave = speed_to_octave(min);
+        let octave_span = speed_to_octave(max) - min_octave;
+
+        Self {
+            min,
+            max,
+            min_octave,
+            octave_span,
+            octave_span_recip: 1.0 / octave_span,
+        }
+    }
+
+    pub fn constrain(&self, value: f32) -> f32 {
+        if value <= self.min {
+            self.min
+        } else if value >= self.max {
+            self.max
+        } else {
+            value
+        }
+    }
+
+    pub fn default_normal_param(&self) -> NormalParam {
+        self.normal_param(1., 1.)
+    }
+}
+
+impl TaperedRange for SpeedRange {
+    type Value = f32;
+
+    fn map_to_normal(&self, value: f32) -> Normal {
+        let speed_octave = speed_to_octave(self.constrain(value));
+        ((speed_octave - self.min_octave) * self.octave_span_recip).into()
+    }
+
+    fn unmap_to_value(&self, normal: Normal) -> f32 {
+        let speed_octave = self.min_octave + normal.as_f32() * self.octave_span;
+
+        // rounding may land a hair outside of the range at its ends
+        self.constrain(octave_to_speed(speed_octave))
+    }
+}
+
+impl Default for SpeedRange {
+    fn default() -> Self {
+        SpeedRange::new(MIN_SPEED, MAX_SPEED)
+    }
+}
+
+#[inline]
+fn octave_to_speed(value: f32) -> f32 {
+    2.0_f32.powf(value)
+}
+
+#[inline]
+fn speed_to_octave(speed: f32) -> f32 {
+    speed.log2()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseSpeedError {
+    Empty,
+    Invalid(String),
+    NotPositive(String),
+}
+
+impl fmt::Display for ParseSpeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSpeedError::Empty => write!(f, "empty speed"),
+            ParseSpeedError::Invalid(input) => write!(f, "invalid speed \"{}\"", input),
+            ParseSpeedError::NotPositive(input) => write!(f, "speed \"{}\" is not positive", input),
+        }
+    }
+}
+
+impl std::error::Error for ParseSpeedError {}
+
+/// Parses a typed speed such as "1.5", "x3", "1/4" or "÷2"
+///
+/// Input matching the `text`, `text_mark` or exact ratio of a speed of `table`
+/// gives that [`SpeedValue::Quantized`] speed, anything else is constrained to
+/// `range`
+pub fn parse_speed(
+    input: &str,
+    table: &SpeedTable,
+    range: &SpeedRange,
+) -> Result<SpeedValue, ParseSpeedError> {
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Err(ParseSpeedError::Empty);
+    }
+
+    let quantized_index = table
+        .speeds()
+        .iter()
+        .position(|speed| speed.text == input || speed.text_mark.as_deref() == Some(input))
+        .or_else(|| {
+            let ratio = input.parse::<Ratio>().ok()?;
+            table.speeds().iter().position(|speed| speed.ratio == ratio)
+        });
+
+    if let Some(speed) = quantized_index.and_then(|index| table.quantized(index)) {
+        return Ok(speed);
+    }
+
+    let invalid = || ParseSpeedError::Invalid(input.to_string());
+    let parse_number = |number: &str| number.trim().parse::<f32>().map_err(|_| invalid());
+
+    let value = if let Some(divisor) = input.strip_prefix('÷').or_else(|| input.strip_prefix('/')) {
+        1. / parse_number(divisor)?
+    } else if let Some(factor) = input.strip_prefix(['x', 'X', '×']) {
+        parse_number(factor)?
+    } else if let Some((numerator, denominator)) = input.split_once('/') {
+        parse_number(numerator)? / parse_number(denominator)?
+    } else {
+        parse_number(input)?
+    };
+
+    if !value.is_finite() {
+        return Err(invalid());
+    }
+
+    if value <= 0. {
+        return Err(ParseSpeedError::NotPositive(input.to_string()));
+    }
+
+    Ok(SpeedValue::Unquantized(range.constrain(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    /// Relative tolerance of a round trip, a few ulps of the log2/exp2 pair
+    const TOLERANCE: f32 = 1e-5;
+
+    /// Ranges within the supported 1/8..8, not necessarily centered around 1
+    fn speed_range() -> impl Strategy<Value = SpeedRange> {
+        (0.125f32..7.9, 0.01f32..1.0).prop_map(|(min, position)| {
+            let max = min + (8. - min) * position;
+            SpeedRange::new(min, max.max(min * 1.01))
+        })
+    }
+
+    fn assert_round_trip(range: &SpeedRange, value: f32) {
+        let normal = range.map_to_normal(value);
+        let round_trip = range.unmap_to_value(normal);
+
+        assert!(
+            ((round_trip - value) / value).abs() <= TOLERANCE,
+            "{} -> {} -> {} in {:?}",
+            value,
+            normal.as_f32(),
+            round_trip,
+            range
+        );
+    }
+
+    #[test]
+    fn asymmetric_range_ends() {
+        let range = SpeedRange::new(0.5, 4.);
+
+        assert_eq!(range.map_to_normal(0.5).as_f32(), 0.);
+        assert_eq!(range.map_to_normal(4.).as_f32(), 1.);
+        assert!((range.map_to_normal(1.).as_f32() - 1. / 3.).abs() < TOLERANCE);
+        assert_eq!(range.unmap_to_value(Normal::MIN), 0.5);
+        assert_eq!(range.unmap_to_value(Normal::MAX), 4.);
+    }
+
+    #[test]
+    fn quantized_speeds_round_trip() {
+        let range = SpeedRange::default();
+
+        for speed in SpeedTable::default().speeds() {
+            assert_round_trip(&range, speed.value());
+        }
+    }
+
+    #[test]
+    fn parses_division_texts() {
+        let table = SpeedTable::default();
+        let range = SpeedRange::default();
+        let dotted = table.speeds().iter().position(|speed| speed.text == "3/2").unwrap();
+
+        let dotted = SpeedValue::Quantized { index: dotted, ratio: Ratio::new(3, 2) };
+
+        assert_eq!(parse_speed("3/2", &table, &range), Ok(dotted));
+        assert_eq!(parse_speed("6/4", &table, &range), Ok(dotted));
+        assert_eq!(parse_speed("1.5", &table, &range), Ok(SpeedValue::Unquantized(1.5)));
+    }
+
+    proptest! {
+        #[test]
+        fn default_range_round_trips(value in 0.125f32..=8.) {
+            assert_round_trip(&SpeedRange::default(), value);
+        }
+
+        #[test]
+        fn half_to_four_round_trips(value in 0.5f32..=4.) {
+            assert_round_trip(&SpeedRange::new(0.5, 4.), value);
+        }
+
+        #[test]
+        fn custom_range_round_trips(range in speed_range(), position in 0f32..=1.) {
+            let value = range.min * (range.max / range.min).powf(position);
+            assert_round_trip(&range, range.constrain(value));
+        }
+
+        #[test]
+        fn normal_round_trips(range in speed_range(), normal in 0f32..=1.) {
+            let normal = Normal::from(normal);
+            let round_trip = range.map_to_normal(range.unmap_to_value(normal));
+
+            prop_assert!((round_trip.as_f32() - normal.as_f32()).abs() <= 1e-4);
+        }
+
+        #[test]
+        fn mapping_is_monotonic(range in speed_range(), a in 0.125f32..=8., b in 0.125f32..=8.) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+
+            prop_assert!(range.map_to_normal(low) <= range.map_to_normal(high));
+        }
+
+        #[test]
+        fn out_of_range_values_are_constrained(range in speed_range(), value in 0.001f32..100.) {
+            let normal = range.map_to_normal(value).as_f32();
+
+            prop_assert!((0.0..=1.0).contains(&normal));
+            prop_assert_eq!(range.unmap_to_value(range.map_to_normal(value)), range.unmap_to_value(normal.into()));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Exact ratios of unsigned integers, for speeds that `f32` can't represent
+//! such as 1/3
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Mul;
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A fraction kept in its lowest terms, so that equal ratios compare equal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ratio {
+    numerator: u32,
+    denominator: u32,
+}
+
+impl Ratio {
+    pub const ONE: Ratio = Ratio {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    /// Panics when `denominator` is zero
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        Self::checked_new(numerator, denominator).expect("ratio with a zero denominator")
+    }
+
+    pub fn checked_new(numerator: u32, denominator: u32) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        let divisor = gcd(numerator, denominator);
+
+        Some(Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    pub fn numerator(self) -> u32 {
+        self.numerator
+    }
+
+    pub fn denominator(self) -> u32 {
+        self.denominator
+    }
+
+    pub fn as_f32(self) -> f32 {
+        self.numerator as f32 / self.denominator as f32
+    }
+
+    /// Panics when the ratio is zero
+    pub fn recip(self) -> Self {
+        Self::new(self.denominator, self.numerator)
+    }
+
+    /// `None` when the product doesn't fit in `u32`s once reduced
+    pub fn checked_mul(self, other: Ratio) -> Option<Self> {
+        // cross reduce first, both ratios are already in their lowest terms
+        let left = gcd(self.numerator, other.denominator).max(1);
+        let right = gcd(other.numerator, self.denominator).max(1);
+
+        let numerator = (self.numerator / left).checked_mul(other.numerator / right)?;
+        let denominator = (self.denominator / right).checked_mul(other.denominator / left)?;
+
+        Self::checked_new(numerator, denominator)
+    }
+}
+
+impl Default for Ratio {
+    fn default() -> Self {
+        Ratio::ONE
+    }
+}
+
+impl Mul for Ratio {
+    type Output = Ratio;
+
+    /// Panics on overflow, see [`Ratio::checked_mul`]
+    fn mul(self, other: Ratio) -> Ratio {
+        self.checked_mul(other).expect("ratio multiplication overflow")
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let left = self.numerator as u64 * other.denominator as u64;
+        let right = other.numerator as u64 * self.denominator as u64;
+
+        left.cmp(&right)
+    }
+}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Ratio {
+    /// Whole ratios are written without their denominator
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRatioError;
+
+impl fmt::Display for ParseRatioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a ratio such as \"3\" or \"3/2\"")
+    }
+}
+
+impl std::error::Error for ParseRatioError {}
+
+impl FromStr for Ratio {
+    type Err = ParseRatioError;
+
+    /// Parses "3" or "3/2"
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (numerator, denominator) = input.split_once('/').unwrap_or((input, "1"));
+
+        let numerator = numerator.trim().parse().map_err(|_| ParseRatioError)?;
+        let denominator = denominator.trim().parse().map_err(|_| ParseRatioError)?;
+
+        Ratio::checked_new(numerator, denominator).ok_or(ParseRatioError)
+    }
+}
+
+/// Written as a string such as "3/2", like [`Ratio`]'s `Display`
+impl Serialize for Ratio {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ratio {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Greatest common divisor, `gcd(0, 0)` is 0
+pub fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    #[test]
+    fn reduces_to_lowest_terms() {
+        let ratio = Ratio::new(6, 8);
+
+        assert_eq!((ratio.numerator(), ratio.denominator()), (3, 4));
+        assert_eq!(Ratio::new(2, 2), Ratio::ONE);
+        assert_eq!(Ratio::new(0, 5), Ratio::new(0, 1));
+        assert_eq!(Ratio::checked_new(1, 0), None);
+    }
+
+    #[test]
+    fn multiplies_exactly() {
+        let triplet = Ratio::new(2, 3);
+
+        assert_eq!(triplet * Ratio::new(3, 2), Ratio::ONE);
+        assert_eq!(Ratio::new(1, 4) * triplet, Ratio::new(1, 6));
+        assert_eq!(Ratio::new(u32::MAX, 2).checked_mul(Ratio::new(2, u32::MAX)), Some(Ratio::ONE));
+        assert_eq!(Ratio::new(u32::MAX, 1).checked_mul(Ratio::new(2, 1)), None);
+    }
+
+    #[test]
+    fn formats_and_parses() {
+        assert_eq!(Ratio::new(3, 2).to_string(), "3/2");
+        assert_eq!(Ratio::new(8, 2).to_string(), "4");
+        assert_eq!("6/4".parse(), Ok(Ratio::new(3, 2)));
+        assert_eq!(" 3 ".parse(), Ok(Ratio::new(3, 1)));
+        assert_eq!("1/0".parse::<Ratio>(), Err(ParseRatioError));
+        assert_eq!("1.5".parse::<Ratio>(), Err(ParseRatioError));
+    }
+
+    proptest! {
+        #[test]
+        fn ordering_matches_values(a in 1u32..1000, b in 1u32..1000, c in 1u32..1000, d in 1u32..1000) {
+            let (left, right) = (Ratio::new(a, b), Ratio::new(c, d));
+
+            prop_assert_eq!(left.cmp(&right), (a as u64 * d as u64).cmp(&(c as u64 * b as u64)));
+        }
+
+        #[test]
+        fn recip_is_the_inverse(a in 1u32..10_000, b in 1u32..10_000) {
+            prop_assert_eq!(Ratio::new(a, b) * Ratio::new(a, b).recip(), Ratio::ONE);
+        }
+
+        #[test]
+        fn display_round_trips(a in 0u32..10_000, b in 1u32..10_000) {
+            let ratio = Ratio::new(a, b);
+            prop_assert_eq!(ratio.to_string().parse(), Ok(ratio));
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Glides between free speeds
+//!
+//! A [`SpeedSmoother`] sits on the consumer side of the controls: it is told
+//! every new [`SpeedValue`] and is advanced by the audio or animation side,
+//! which reads a speed that ramps to the new value instead of jumping to it.
+//! Quantized speeds are musical steps and are taken at once.
+
+use std::time::Duration;
+
+use super::SpeedValue;
+
+/// How many time constants an exponential ramp lasts, it is within 1% of its
+/// target after that
+const EXPONENTIAL_TIME_CONSTANTS: f64 = 5.0;
+
+/// Relative distance to the target under which a ramp is over
+const SETTLE_EPSILON: f32 = 1e-4;
+
+/// The curve followed from one speed to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ramp {
+    /// Equal speed steps in equal times
+    Linear,
+    /// Quick at first, then slowing down as the target gets closer, like a
+    /// one-pole filter
+    Exponential,
+    /// Equal speed ratios in equal times, a glide from 1 to 4 goes through 2
+    /// half way
+    #[default]
+    Logarithmic,
+}
+
+/// A speed that ramps to its target over a configurable time
+#[derive(Debug, Clone)]
+pub struct SpeedSmoother {
+    ramp: Ramp,
+    ramp_time: Duration,
+    value: f32,
+    /// The value when the ramp to `target` started
+    start: f32,
+    target: f32,
+    /// Seconds since the ramp to `target` started
+    elapsed: f64,
+}
+
+impl SpeedSmoother {
+    pub fn new(ramp: Ramp, ramp_time: Duration, speed: SpeedValue) -> Self {
+        let value = speed.as_f32();
+
+        Self {
+            ramp,
+            ramp_time,
+            value,
+            start: value,
+            target: value,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn ramp(&self) -> Ramp {
+        self.ramp
+    }
+
+    /// Applies from the next advance, a ramp under way carries on from where
+    /// it is
+    pub fn set_ramp(&mut self, ramp: Ramp) {
+        self.restart();
+        self.ramp = ramp;
+    }
+
+    pub fn ramp_time(&self) -> Duration {
+        self.ramp_time
+    }
+
+    /// A ramp under way ends at once when there is no ramp time anymore
+    pub fn set_ramp_time(&mut self, ramp_time: Duration) {
+        self.restart();
+        self.ramp_time = ramp_time;
+
+        if ramp_time.is_zero() {
+            self.snap(self.target);
+        }
+    }
+
+    /// The smoothed speed
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.value == self.target
+    }
+
+    /// Quantized speeds snap at once, free ones start a ramp from the current
+    /// value
+    pub fn set_target(&mut self, speed: SpeedValue) {
+        match speed {
+            SpeedValue::Quantized { .. } => self.snap(speed.as_f32()),
+            SpeedValue::Unquantized(value) => self.glide(value),
+        }
+    }
+
+    pub fn glide(&mut self, target: f32) {
+        if self.ramp_time.is_zero() {
+            self.snap(target);
+        } else {
+            self.restart();
+            self.target = target;
+        }
+    }
+
+    /// Jumps to `value`, ending any ramp under way
+    pub fn snap(&mut self, value: f32) {
+        self.value = value;
+        self.start = value;
+        self.target = value;
+        self.elapsed = 0.0;
+    }
+
+    /// Advances by `frames` audio frames at `sample_rate`, a zero sample rate
+    /// doesn't move the ramp
+    pub fn advance_frames(&mut self, frames: u64, sample_rate: u32) -> f32 {
+        if sample_rate == 0 {
+            return self.value;
+        }
+
+        self.advance_seconds(frames as f64 / sample_rate as f64)
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) -> f32 {
+        self.advance_seconds(elapsed.as_secs_f64())
+    }
+
+    /// Moves along the ramp and returns the smoothed speed, negative and non
+    /// finite durations don't move it
+    pub fn advance_seconds(&mut self, seconds: f64) -> f32 {
+        if self.is_settled() || !seconds.is_finite() || seconds < 0.0 {
+            return self.value;
+        }
+
+        self.elapsed += seconds;
+
+        let ramp_time = self.ramp_time.as_secs_f64();
+
+        self.value = match self.ramp {
+            Ramp::Linear | Ramp::Logarithmic if self.elapsed >= ramp_time => self.target,
+            Ramp::Linear => {
+                let progress = (self.elapsed / ramp_time) as f32;
+                self.start + (self.target - self.start) * progress
+            }
+            Ramp::Logarithmic => {
+                let progress = (self.elapsed / ramp_time) as f32;
+                let (start, target) = (self.start.log2(), self.target.log2());
+                (start + (target - start) * progress).exp2()
+            }
+            Ramp::Exponential => {
+                let time_constant = ramp_time / EXPONENTIAL_TIME_CONSTANTS;
+                let coefficient = 1.0 - (-seconds / time_constant).exp();
+                let value = self.value + (self.target - self.value) * coefficient as f32;
+
+                if (self.target - value).abs() <= self.target.abs() * SETTLE_EPSILON {
+                    self.target
+                } else {
+                    value
+                }
+            }
+        };
+
+        self.value
+    }
+
+    /// Writes one smoothed speed per audio frame at `sample_rate`, all of
+    /// them the current one at a zero sample rate
+    pub fn fill(&mut self, speeds: &mut [f32], sample_rate: u32) {
+        if sample_rate == 0 {
+            speeds.fill(self.value);
+            return;
+        }
+
+        let frame = 1.0 / sample_rate as f64;
+
+        for speed in speeds {
+            *speed = self.advance_seconds(frame);
+        }
+    }
+
+    /// Starts a new ramp from the current value to the same target
+    fn restart(&mut self) {
+        self.start = self.value;
+        self.elapsed = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speed::Ratio;
+
+    const RAMP_TIME: Duration = Duration::from_millis(100);
+
+    fn smoother(ramp: Ramp) -> SpeedSmoother {
+        SpeedSmoother::new(ramp, RAMP_TIME, SpeedValue::Unquantized(1.))
+    }
+
+    fn assert_close(left: f32, right: f32) {
+        assert!((left - right).abs() < 1e-4, "{} is not {}", left, right);
+    }
+
+    #[test]
+    fn linear_ramps_in_equal_steps() {
+        let mut smoother = smoother(Ramp::Linear);
+        smoother.set_target(SpeedValue::Unquantized(3.));
+
+        assert_close(smoother.advance_seconds(0.025), 1.5);
+        assert_close(smoother.advance_seconds(0.025), 2.);
+        assert_close(smoother.advance_seconds(0.05), 3.);
+        assert!(smoother.is_settled());
+    }
+
+    #[test]
+    fn logarithmic_ramps_in_equal_ratios() {
+        let mut smoother = smoother(Ramp::Logarithmic);
+        smoother.set_target(SpeedValue::Unquantized(4.));
+
+        assert_close(smoother.advance_seconds(0.05), 2.);
+        assert_close(smoother.advance(Duration::from_millis(60)), 4.);
+        assert!(smoother.is_settled());
+    }
+
+    #[test]
+    fn exponential_settles_after_the_ramp_time() {
+        let mut smoother = smoother(Ramp::Exponential);
+        smoother.set_target(SpeedValue::Unquantized(2.));
+
+        let first = smoother.advance_seconds(0.02);
+        let second = smoother.advance_seconds(0.02);
+
+        // the first step is the biggest
+        assert!(first - 1. > second - first);
+
+        let mut speeds = vec![0.; 48_000];
+        smoother.fill(&mut speeds, 48_000);
+
+        assert!(speeds.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(smoother.is_settled());
+    }
+
+    #[test]
+    fn quantized_speeds_snap_immediately() {
+        let mut smoother = smoother(Ramp::Linear);
+        smoother.set_target(SpeedValue::Unquantized(2.));
+        smoother.advance_seconds(0.05);
+
+        smoother.set_target(SpeedValue::Quantized { index: 0, ratio: Ratio::new(1, 2) });
+
+        assert!(smoother.is_settled());
+        assert_eq!(smoother.value(), 0.5);
+    }
+
+    #[test]
+    fn retargeting_carries_on_from_the_current_value() {
+        let mut smoother = smoother(Ramp::Linear);
+        smoother.set_target(SpeedValue::Unquantized(3.));
+        smoother.advance_seconds(0.05);
+
+        smoother.set_target(SpeedValue::Unquantized(1.));
+
+        assert_close(smoother.value(), 2.);
+        assert_close(smoother.advance_seconds(0.05), 1.5);
+    }
+
+    #[test]
+    fn zero_ramp_time_snaps() {
+        let mut smoother = SpeedSmoother::new(Ramp::Exponential, Duration::ZERO, SpeedValue::Unquantized(1.));
+        smoother.set_target(SpeedValue::Unquantized(2.));
+
+        assert_eq!(smoother.value(), 2.);
+    }
+
+    #[test]
+    fn zero_sample_rate_does_not_advance() {
+        let mut smoother = smoother(Ramp::Linear);
+        smoother.set_target(SpeedValue::Unquantized(2.));
+
+        assert_eq!(smoother.advance_frames(0, 0), 1.);
+        assert_eq!(smoother.advance_frames(480, 0), 1.);
+
+        let mut speeds = [0.; 4];
+        smoother.fill(&mut speeds, 0);
+        assert_eq!(speeds, [1.; 4]);
+
+        assert_close(smoother.advance_seconds(0.05), 1.5);
+    }
+
+    #[test]
+    fn removing_the_ramp_time_mid_ramp_snaps() {
+        let mut smoother = smoother(Ramp::Exponential);
+        smoother.set_target(SpeedValue::Unquantized(2.));
+        smoother.advance_seconds(0.02);
+
+        smoother.set_ramp_time(Duration::ZERO);
+
+        assert_eq!(smoother.advance_seconds(0.), 2.);
+        assert!(smoother.is_settled());
+    }
+}
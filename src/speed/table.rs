@@ -0,0 +1,627 @@
+//! The quantized speeds a speed control snaps to
+//!
+//! A [`SpeedTable`] is either generated from musical [`Division`]s or read
+//! from a TOML or JSON file, see [`SpeedTable::load`].
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{Ratio, SpeedRange, SpeedValue, MAX_SPEED, MIN_SPEED};
+use crate::h_slider::normal::Normal;
+use crate::h_slider::taper::TaperedRange;
+use crate::h_slider::Marker;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedSpeedValue {
+    pub ratio: Ratio,
+    pub text: String,
+    pub mark_weight: Option<MarkWeight>,
+    pub text_mark: Option<String>
+}
+
+impl QuantizedSpeedValue {
+    pub fn value(&self) -> f32 {
+        self.ratio.as_f32()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkWeight {
+    Normal,
+    Bold
+}
+
+/// A musical division, applied to each power of two of a [`SpeedTable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Division {
+    Straight,
+    /// Half as long again, x1.5 or x0.75 an octave below
+    Dotted,
+    /// Three in the time of two, x2/3 or x4/3 an octave above
+    Triplet,
+    /// Five in the time of four, x4/5
+    Quintuplet,
+}
+
+impl Division {
+    pub const ALL: [Division; 4] = [
+        Division::Straight,
+        Division::Dotted,
+        Division::Triplet,
+        Division::Quintuplet,
+    ];
+
+    /// The ratio applied to a straight speed
+    pub fn ratio(self) -> Ratio {
+        match self {
+            Division::Straight => Ratio::ONE,
+            Division::Dotted => Ratio::new(3, 2),
+            Division::Triplet => Ratio::new(2, 3),
+            Division::Quintuplet => Ratio::new(4, 5),
+        }
+    }
+
+    /// Glyph drawn above the marks of this division, straight speeds have
+    /// their own
+    fn text_mark(self) -> Option<&'static str> {
+        match self {
+            Division::Straight => None,
+            Division::Dotted => Some("·"),
+            Division::Triplet => Some("³"),
+            Division::Quintuplet => Some("⁵"),
+        }
+    }
+}
+
+/// The speeds a quantized speed control snaps to, sorted from slowest to
+/// fastest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeedTable {
+    speeds: Vec<QuantizedSpeedValue>,
+    default_index: usize,
+}
+
+impl SpeedTable {
+    /// A table of `speeds`, its default is the speed closest to 1
+    pub fn new(speeds: Vec<QuantizedSpeedValue>) -> Self {
+        Self::sorted(speeds, None)
+    }
+
+    /// Reads a table from a `.toml` or `.json` file
+    ///
+    /// ```toml
+    /// [[speeds]]
+    /// numerator = 1
+    /// denominator = 2
+    /// label = "1/2"
+    /// mark = "÷2"
+    /// weight = "normal"
+    ///
+    /// [[speeds]]
+    /// numerator = 1
+    /// denominator = 1
+    /// label = "1"
+    /// weight = "bold"
+    /// default = true
+    /// ```
+    ///
+    /// `mark` and `weight` are optional, the default is the speed closest to 1
+    /// when no entry is marked as such.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SpeedTableError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(SpeedTableError::Io)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(SpeedTableError::UnknownFormat),
+        }
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, SpeedTableError> {
+        Self::from_file(toml::from_str(contents).map_err(SpeedTableError::Toml)?)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, SpeedTableError> {
+        Self::from_file(serde_json::from_str(contents).map_err(SpeedTableError::Json)?)
+    }
+
+    fn from_file(file: SpeedTableFile) -> Result<Self, SpeedTableError> {
+        if file.speeds.is_empty() {
+            return Err(SpeedTableError::Empty);
+        }
+
+        let mut default = None;
+
+        for (index, entry) in file.speeds.iter().enumerate() {
+            let invalid = |problem| SpeedTableError::Entry {
+                index,
+                label: entry.label.clone(),
+                problem,
+            };
+
+            if entry.label.trim().is_empty() {
+                return Err(invalid(EntryProblem::EmptyLabel));
+            }
+
+            if entry.numerator == 0 {
+                return Err(invalid(EntryProblem::Numerator(entry.numerator)));
+            }
+
+            if entry.denominator == 0 {
+                return Err(invalid(EntryProblem::Denominator(entry.denominator)));
+            }
+
+            let ratio = Ratio::new(entry.numerator, entry.denominator);
+            if !in_range(ratio) {
+                return Err(invalid(EntryProblem::OutOfRange(ratio)));
+            }
+
+            for (other_index, other) in file.speeds[..index].iter().enumerate() {
+                if other.label == entry.label {
+                    return Err(invalid(EntryProblem::DuplicateLabel(other_index)));
+                }
+
+                if Ratio::new(other.numerator, other.denominator) == ratio {
+                    return Err(invalid(EntryProblem::DuplicateValue(other_index)));
+                }
+            }
+
+            if entry.default {
+                if let Some(other_index) = default {
+                    return Err(invalid(EntryProblem::SecondDefault(other_index)));
+                }
+
+                default = Some(index);
+            }
+        }
+
+        let speeds = file
+            .speeds
+            .into_iter()
+            .map(|entry| QuantizedSpeedValue {
+                ratio: Ratio::new(entry.numerator, entry.denominator),
+                text: entry.label,
+                mark_weight: entry.weight,
+                text_mark: entry.mark,
+            })
+            .collect();
+
+        Ok(Self::sorted(speeds, default))
+    }
+
+    /// Sorts `speeds` by value, `default` is an index into the unsorted
+    /// speeds
+    fn sorted(speeds: Vec<QuantizedSpeedValue>, default: Option<usize>) -> Self {
+        assert!(!speeds.is_empty());
+
+        let mut speeds: Vec<_> = speeds.into_iter().enumerate().collect();
+        speeds.sort_by_key(|(_, speed)| speed.ratio);
+
+        let default_index = match default {
+            Some(default) => speeds.iter().position(|(index, _)| *index == default).unwrap(),
+            None => speeds
+                .iter()
+                .enumerate()
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    a.value().log2().abs().total_cmp(&b.value().log2().abs())
+                })
+                .map(|(index, _)| index)
+                .unwrap(),
+        };
+
+        Self {
+            speeds: speeds.into_iter().map(|(_, speed)| speed).collect(),
+            default_index,
+        }
+    }
+
+    /// Every power of two from 1/8 to 8, and the given `divisions` of them
+    /// that fall within that range
+    pub fn musical(divisions: &[Division]) -> Self {
+        let mut speeds = Vec::new();
+
+        for octave in -3i32..=3 {
+            let power = if octave < 0 {
+                Ratio::new(1, 1 << -octave)
+            } else {
+                Ratio::new(1 << octave, 1)
+            };
+
+            for &division in divisions {
+                let ratio = power * division.ratio();
+
+                if !in_range(ratio) {
+                    continue;
+                }
+
+                let (mark_weight, text_mark) = match division {
+                    Division::Straight => {
+                        let text_mark = match octave.cmp(&0) {
+                            std::cmp::Ordering::Less => format!("÷{}", ratio.denominator()),
+                            std::cmp::Ordering::Equal => "1".to_string(),
+                            std::cmp::Ordering::Greater => format!("x{}", ratio.numerator()),
+                        };
+                        let mark_weight = if octave.abs() % 3 == 0 {
+                            MarkWeight::Bold
+                        } else {
+                            MarkWeight::Normal
+                        };
+
+                        (mark_weight, Some(text_mark))
+                    }
+                    _ => (MarkWeight::Normal, division.text_mark().map(str::to_string)),
+                };
+
+                speeds.push(QuantizedSpeedValue {
+                    ratio,
+                    text: ratio.to_string(),
+                    mark_weight: Some(mark_weight),
+                    text_mark,
+                });
+            }
+        }
+
+        Self::new(speeds)
+    }
+
+    /// The musical table with every `1/n` and `n` up to 8 that it misses,
+    /// so the integer ratios stay within reach
+    pub fn musical_with_integers(divisions: &[Division]) -> Self {
+        let mut speeds = Self::musical(divisions).speeds;
+
+        for n in 1..=8 {
+            for ratio in [Ratio::new(1, n), Ratio::new(n, 1)] {
+                if speeds.iter().any(|speed| speed.ratio == ratio) {
+                    continue;
+                }
+
+                speeds.push(QuantizedSpeedValue {
+                    ratio,
+                    text: ratio.to_string(),
+                    mark_weight: Some(MarkWeight::Normal),
+                    text_mark: None,
+                });
+            }
+        }
+
+        Self::new(speeds)
+    }
+
+    pub fn speeds(&self) -> &[QuantizedSpeedValue] {
+        &self.speeds
+    }
+
+    pub fn get(&self, index: usize) -> Option<&QuantizedSpeedValue> {
+        self.speeds.get(index)
+    }
+
+    /// The speed at `index` as a [`SpeedValue`]
+    pub fn quantized(&self, index: usize) -> Option<SpeedValue> {
+        self.get(index).map(|speed| SpeedValue::Quantized { index, ratio: speed.ratio })
+    }
+
+    pub fn len(&self) -> usize {
+        self.speeds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.speeds.is_empty()
+    }
+
+    /// Index of the speed marked as the default by the table file, or of the
+    /// speed closest to 1 when none is
+    pub fn default_index(&self) -> usize {
+        self.default_index
+    }
+
+    /// Where each speed of the table sits on `range`
+    pub fn normals(&self, range: &SpeedRange) -> Vec<f32> {
+        self.speeds
+            .iter()
+            .map(|speed| range.map_to_normal(speed.value()).as_f32())
+            .collect()
+    }
+
+    /// Index of the speed closest to `normal` on `range`
+    pub fn closest_index(&self, normal: Normal, range: &SpeedRange) -> usize {
+        let distance = |speed: &QuantizedSpeedValue| {
+            (range.map_to_normal(speed.value()).as_f32() - normal.as_f32()).abs()
+        };
+
+        self.speeds
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(index, _)| index)
+            .unwrap_or(self.default_index)
+    }
+
+    /// A mark for each speed of the table on `range`
+    pub fn markers(&self, range: &SpeedRange) -> Vec<Marker> {
+        self.speeds
+            .iter()
+            .map(|speed| {
+                (
+                    range.map_to_normal(speed.value()),
+                    speed.text_mark.clone(),
+                    speed.mark_weight,
+                )
+            })
+            .collect()
+    }
+}
+
+impl Default for SpeedTable {
+    fn default() -> Self {
+        SpeedTable::musical_with_integers(&Division::ALL)
+    }
+}
+
+/// The layout of a speed table file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpeedTableFile {
+    speeds: Vec<SpeedEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpeedEntry {
+    numerator: u32,
+    denominator: u32,
+    label: String,
+    mark: Option<String>,
+    weight: Option<MarkWeight>,
+    #[serde(default)]
+    default: bool,
+}
+
+#[derive(Debug)]
+pub enum SpeedTableError {
+    Io(io::Error),
+    UnknownFormat,
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    Empty,
+    /// The entry at `index` in the file, and its label
+    Entry {
+        index: usize,
+        label: String,
+        problem: EntryProblem,
+    },
+}
+
+/// What's wrong with an entry of a speed table file, other entries are given
+/// by their index
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryProblem {
+    EmptyLabel,
+    Numerator(u32),
+    Denominator(u32),
+    OutOfRange(Ratio),
+    DuplicateLabel(usize),
+    DuplicateValue(usize),
+    SecondDefault(usize),
+}
+
+impl fmt::Display for SpeedTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpeedTableError::Io(error) => write!(f, "{}", error),
+            SpeedTableError::UnknownFormat => write!(f, "expected a .toml or .json file"),
+            SpeedTableError::Toml(error) => write!(f, "{}", error),
+            SpeedTableError::Json(error) => write!(f, "{}", error),
+            SpeedTableError::Empty => write!(f, "no speeds"),
+            SpeedTableError::Entry { index, label, problem } => {
+                write!(f, "speeds[{}] (\"{}\"): {}", index, label, problem)
+            }
+        }
+    }
+}
+
+impl fmt::Display for EntryProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryProblem::EmptyLabel => write!(f, "empty label"),
+            EntryProblem::Numerator(numerator) => {
+                write!(f, "numerator {} is not positive", numerator)
+            }
+            EntryProblem::Denominator(denominator) => {
+                write!(f, "denominator {} is not positive", denominator)
+            }
+            EntryProblem::OutOfRange(value) => write!(
+                f,
+                "speed {} is outside of {}..{}",
+                value, MIN_SPEED, MAX_SPEED
+            ),
+            EntryProblem::DuplicateLabel(index) => write!(f, "same label as speeds[{}]", index),
+            EntryProblem::DuplicateValue(index) => write!(f, "same speed as speeds[{}]", index),
+            EntryProblem::SecondDefault(index) => {
+                write!(f, "speeds[{}] is already the default", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpeedTableError {}
+
+fn in_range(ratio: Ratio) -> bool {
+    (MIN_SPEED..=MAX_SPEED).contains(&ratio.as_f32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn musical_table_has_divisions() {
+        let table = SpeedTable::default();
+        let texts: Vec<_> = table.speeds().iter().map(|speed| speed.text.as_str()).collect();
+
+        for text in [
+            "1/8", "1/7", "3/16", "1/5", "2/3", "3/4", "4/5", "1", "4/3", "3/2", "5", "7", "8",
+        ] {
+            assert!(texts.contains(&text), "{} missing from {:?}", text, texts);
+        }
+
+        assert!(table.speeds().windows(2).all(|pair| pair[0].value() < pair[1].value()));
+        assert_eq!(table.speeds()[table.default_index()].value(), 1.);
+        assert_eq!(table.speeds().first().unwrap().text_mark.as_deref(), Some("÷8"));
+        assert_eq!(table.speeds().last().unwrap().text_mark.as_deref(), Some("x8"));
+
+        let mark = |text| table.speeds().iter().find(|speed| speed.text == text).unwrap().text_mark.as_deref();
+        assert_eq!(mark("3/4"), Some("·"));
+        assert_eq!(mark("2/3"), Some("³"));
+        assert_eq!(mark("4/5"), Some("⁵"));
+    }
+
+    #[test]
+    fn straight_table_is_powers_of_two() {
+        let table = SpeedTable::musical(&[Division::Straight]);
+        let values: Vec<_> = table.speeds().iter().map(QuantizedSpeedValue::value).collect();
+
+        assert_eq!(values, vec![0.125, 0.25, 0.5, 1., 2., 4., 8.]);
+        assert_eq!(table.default_index(), 3);
+    }
+
+    #[test]
+    fn markers_follow_the_table() {
+        let table = SpeedTable::default();
+        let range = SpeedRange::new(0.5, 4.);
+        let markers = table.markers(&range);
+
+        assert_eq!(markers.len(), table.len());
+        assert_eq!(table.normals(&range).len(), table.len());
+
+        for (marker, speed) in markers.iter().zip(table.speeds()) {
+            assert_eq!(marker.0, range.map_to_normal(speed.value()));
+            assert_eq!(marker.1, speed.text_mark);
+        }
+    }
+
+    #[test]
+    fn closest_index_follows_the_range() {
+        let table = SpeedTable::musical(&[Division::Straight]);
+        let range = SpeedRange::default();
+
+        assert_eq!(table.closest_index(Normal::MIN, &range), 0);
+        assert_eq!(table.closest_index(Normal::MAX, &range), table.len() - 1);
+        assert_eq!(table.closest_index(range.map_to_normal(1.9), &range), 4);
+    }
+
+    fn load_error(contents: &str) -> String {
+        SpeedTable::from_toml(contents).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn loads_toml_with_a_default() {
+        let table = SpeedTable::from_toml(
+            r#"
+            [[speeds]]
+            numerator = 2
+            denominator = 1
+            label = "2"
+            mark = "x2"
+            weight = "bold"
+            default = true
+
+            [[speeds]]
+            numerator = 1
+            denominator = 2
+            label = "1/2"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.speeds()[0].text, "1/2");
+        assert_eq!(table.speeds()[0].mark_weight, None);
+        assert_eq!(table.speeds()[1].text_mark.as_deref(), Some("x2"));
+        assert_eq!(table.speeds()[1].mark_weight, Some(MarkWeight::Bold));
+        assert_eq!(table.default_index(), 1);
+    }
+
+    #[test]
+    fn loads_json() {
+        let table = SpeedTable::from_json(
+            r#"{ "speeds": [
+                { "numerator": 1, "denominator": 1, "label": "1" },
+                { "numerator": 3, "denominator": 2, "label": "3/2", "mark": "·" }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(table.default_index(), 0);
+        assert_eq!(table.speeds()[1].value(), 1.5);
+    }
+
+    #[test]
+    fn errors_point_to_the_entry() {
+        let entries = r#"
+            [[speeds]]
+            numerator = 1
+            denominator = 1
+            label = "1"
+            default = true
+        "#;
+
+        let error = load_error(&format!(
+            "{}{}",
+            entries,
+            "[[speeds]]\nnumerator = 1\ndenominator = 0\nlabel = \"oops\""
+        ));
+        assert_eq!(error, "speeds[1] (\"oops\"): denominator 0 is not positive");
+
+        let error = load_error(&format!(
+            "{}{}",
+            entries,
+            "[[speeds]]\nnumerator = 16\ndenominator = 1\nlabel = \"16\""
+        ));
+        assert_eq!(error, "speeds[1] (\"16\"): speed 16 is outside of 0.125..8");
+
+        let error = load_error(&format!(
+            "{}{}",
+            entries,
+            "[[speeds]]\nnumerator = 2\ndenominator = 2\nlabel = \"2/2\""
+        ));
+        assert_eq!(error, "speeds[1] (\"2/2\"): same speed as speeds[0]");
+
+        let error = load_error(&format!(
+            "{}{}",
+            entries,
+            "[[speeds]]\nnumerator = 2\ndenominator = 1\nlabel = \"1\""
+        ));
+        assert_eq!(error, "speeds[1] (\"1\"): same label as speeds[0]");
+
+        let error = load_error(&format!(
+            "{}{}",
+            entries,
+            "[[speeds]]\nnumerator = 2\ndenominator = 1\nlabel = \"2\"\ndefault = true"
+        ));
+        assert_eq!(error, "speeds[1] (\"2\"): speeds[0] is already the default");
+    }
+
+    #[test]
+    fn syntax_errors_keep_their_location() {
+        let error = load_error("[[speeds]]\nnumerator = 1\ndenominator = 1\nlable = \"1\"");
+
+        assert!(error.contains("line 4"), "{}", error);
+        assert!(matches!(SpeedTable::from_toml("speeds = []"), Err(SpeedTableError::Empty)));
+    }
+
+    #[test]
+    fn shipped_tables_load() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("speed_tables");
+
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            SpeedTable::load(&path).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        }
+    }
+}
@@ -0,0 +1,505 @@
+//! A transport clock driven by a [`SpeedValue`]
+//!
+//! The [`Transport`] turns elapsed time into musical positions at a tempo,
+//! scaled by the current speed. Time comes from a [`Clock`], from audio
+//! frames at a sample rate, or from durations handed to it directly, so the
+//! same code runs under a real-time engine and under a [`FakeClock`] in
+//! tests.
+//!
+//! Quantized speed changes wait for the next beat or bar boundary, free
+//! speed changes apply at once.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use super::SpeedValue;
+
+/// Positions closer than this to a boundary, in beats, are on it
+const BOUNDARY_EPSILON: f64 = 1e-9;
+
+/// A monotonic source of time
+pub trait Clock {
+    /// Time elapsed since an arbitrary origin
+    fn now(&self) -> Duration;
+}
+
+/// The wall clock
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// A clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct FakeClock {
+    now: Cell<Duration>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// Where quantized speed changes take effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Boundary {
+    Beat,
+    #[default]
+    Bar,
+}
+
+/// A musical position, bars, beats and sub-beats count from 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub bar: u64,
+    pub beat: u32,
+    pub sub_beat: u32,
+    /// Progress through the current sub-beat, from 0 to 1
+    pub phase: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportEvent {
+    /// A beat started `offset` seconds into the advance that crossed it
+    Beat { position: Position, offset: f64 },
+    /// A sub-beat that isn't on a beat started
+    SubBeat { position: Position, offset: f64 },
+    /// A speed change took effect
+    SpeedChanged { speed: SpeedValue, offset: f64 },
+}
+
+/// Beat positions at a tempo, scaled by a speed
+#[derive(Debug, Clone)]
+pub struct Transport {
+    bpm: f64,
+    beats_per_bar: u32,
+    sub_beats: u32,
+    boundary: Boundary,
+    speed: SpeedValue,
+    pending_speed: Option<SpeedValue>,
+    /// Position in beats since the start
+    beats: f64,
+    /// Clock reading of the previous [`Transport::tick`]
+    last_tick: Option<Duration>,
+}
+
+impl Transport {
+    /// A transport at `bpm` in 4/4 with four sub-beats per beat
+    pub fn new(bpm: f64, speed: SpeedValue) -> Self {
+        assert!(bpm > 0.0);
+
+        Self {
+            bpm,
+            beats_per_bar: 4,
+            sub_beats: 4,
+            boundary: Boundary::default(),
+            speed,
+            pending_speed: None,
+            beats: 0.0,
+            last_tick: None,
+        }
+    }
+
+    pub fn beats_per_bar(mut self, beats_per_bar: u32) -> Self {
+        assert!(beats_per_bar > 0);
+        self.beats_per_bar = beats_per_bar;
+        self
+    }
+
+    pub fn sub_beats(mut self, sub_beats: u32) -> Self {
+        assert!(sub_beats > 0);
+        self.sub_beats = sub_beats;
+        self
+    }
+
+    pub fn boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    pub fn set_bpm(&mut self, bpm: f64) {
+        assert!(bpm > 0.0);
+        self.bpm = bpm;
+    }
+
+    /// The speed in effect, a quantized change may still be pending
+    pub fn speed(&self) -> SpeedValue {
+        self.speed
+    }
+
+    pub fn pending_speed(&self) -> Option<SpeedValue> {
+        self.pending_speed
+    }
+
+    /// Free speeds apply at once, quantized ones at the next boundary
+    ///
+    /// A quantized speed set while already on a boundary applies at once too.
+    pub fn set_speed(&mut self, speed: SpeedValue) {
+        match speed {
+            SpeedValue::Quantized { .. } if !self.is_on_boundary() => {
+                self.pending_speed = Some(speed);
+            }
+            _ => {
+                self.speed = speed;
+                self.pending_speed = None;
+            }
+        }
+    }
+
+    /// Position in beats since the start
+    pub fn beats(&self) -> f64 {
+        self.beats
+    }
+
+    pub fn position(&self) -> Position {
+        position_at(self.beats, self.beats_per_bar, self.sub_beats)
+    }
+
+    /// Goes back to the start, a pending speed applies at once
+    pub fn reset(&mut self) {
+        self.beats = 0.0;
+        self.last_tick = None;
+
+        if let Some(speed) = self.pending_speed.take() {
+            self.speed = speed;
+        }
+    }
+
+    /// Advances by the time `clock` moved since the previous tick, the first
+    /// tick only reads the clock
+    pub fn tick(&mut self, clock: &impl Clock) -> Vec<TransportEvent> {
+        let now = clock.now();
+        let elapsed = self
+            .last_tick
+            .map(|last_tick| now.saturating_sub(last_tick))
+            .unwrap_or_default();
+
+        self.last_tick = Some(now);
+
+        self.advance(elapsed)
+    }
+
+    /// Advances by `frames` audio frames at `sample_rate`, a zero sample rate
+    /// doesn't move the transport
+    pub fn advance_frames(&mut self, frames: u64, sample_rate: u32) -> Vec<TransportEvent> {
+        if sample_rate == 0 {
+            return Vec::new();
+        }
+
+        self.advance_seconds(frames as f64 / sample_rate as f64)
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) -> Vec<TransportEvent> {
+        self.advance_seconds(elapsed.as_secs_f64())
+    }
+
+    /// Advances by `seconds`, negative and non finite durations don't move
+    /// the transport
+    pub fn advance_seconds(&mut self, seconds: f64) -> Vec<TransportEvent> {
+        if !seconds.is_finite() || seconds <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        let mut offset = 0.0;
+
+        while offset < seconds {
+            let beats_per_second = self.bpm / 60.0 * speed_factor(self.speed);
+            let next_sub_beat = self.next_multiple_of(1.0 / self.sub_beats as f64);
+
+            let target = self.beats + (seconds - offset) * beats_per_second;
+
+            if target + BOUNDARY_EPSILON < next_sub_beat {
+                self.beats = target;
+                break;
+            }
+
+            offset += (next_sub_beat - self.beats) / beats_per_second;
+            self.beats = next_sub_beat;
+
+            let position = self.position();
+            events.push(if position.sub_beat == 0 {
+                TransportEvent::Beat { position, offset }
+            } else {
+                TransportEvent::SubBeat { position, offset }
+            });
+
+            if let Some(speed) = self.pending_speed {
+                if self.is_on_boundary() {
+                    self.speed = speed;
+                    self.pending_speed = None;
+                    events.push(TransportEvent::SpeedChanged { speed, offset });
+                }
+            }
+        }
+
+        events
+    }
+
+    fn boundary_length(&self) -> f64 {
+        match self.boundary {
+            Boundary::Beat => 1.0,
+            Boundary::Bar => self.beats_per_bar as f64,
+        }
+    }
+
+    fn is_on_boundary(&self) -> bool {
+        let length = self.boundary_length();
+        let nearest = (self.beats / length).round() * length;
+
+        (self.beats - nearest).abs() <= BOUNDARY_EPSILON
+    }
+
+    /// The first multiple of `step` after the current position
+    fn next_multiple_of(&self, step: f64) -> f64 {
+        let next = ((self.beats + BOUNDARY_EPSILON) / step).floor() + 1.0;
+        next * step
+    }
+}
+
+fn speed_factor(speed: SpeedValue) -> f64 {
+    match speed {
+        SpeedValue::Quantized { ratio, .. } => ratio.numerator() as f64 / ratio.denominator() as f64,
+        SpeedValue::Unquantized(value) => value as f64,
+    }
+}
+
+fn position_at(beats: f64, beats_per_bar: u32, sub_beats: u32) -> Position {
+    // land on the boundary a rounding error away
+    let sub_beat_position = beats * sub_beats as f64;
+    let rounded = sub_beat_position.round();
+    let sub_beat_position = if (sub_beat_position - rounded).abs() <= BOUNDARY_EPSILON {
+        rounded
+    } else {
+        sub_beat_position
+    };
+
+    let total_sub_beats = sub_beat_position.floor() as u64;
+    let total_beats = total_sub_beats / sub_beats as u64;
+
+    Position {
+        bar: total_beats / beats_per_bar as u64,
+        beat: (total_beats % beats_per_bar as u64) as u32,
+        sub_beat: (total_sub_beats % sub_beats as u64) as u32,
+        phase: sub_beat_position - sub_beat_position.floor(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::speed::Ratio;
+
+    fn quantized(numerator: u32, denominator: u32) -> SpeedValue {
+        SpeedValue::Quantized {
+            index: 0,
+            ratio: Ratio::new(numerator, denominator),
+        }
+    }
+
+    fn beats(events: &[TransportEvent]) -> Vec<(u64, u32)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                TransportEvent::Beat { position, .. } => Some((position.bar, position.beat)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counts_beats_at_tempo() {
+        // 120 bpm is two beats a second
+        let mut transport = Transport::new(120., quantized(1, 1)).sub_beats(1);
+
+        let events = transport.advance(Duration::from_secs(3));
+
+        assert_eq!(beats(&events), vec![(0, 1), (0, 2), (0, 3), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(transport.beats(), 6.);
+        assert_eq!(
+            transport.position(),
+            Position { bar: 1, beat: 2, sub_beat: 0, phase: 0. }
+        );
+    }
+
+    #[test]
+    fn speed_scales_beats() {
+        let mut transport = Transport::new(120., quantized(3, 2));
+
+        transport.advance(Duration::from_secs(2));
+        assert!((transport.beats() - 6.).abs() < 1e-9);
+
+        let mut transport = Transport::new(120., SpeedValue::Unquantized(0.5));
+
+        transport.advance(Duration::from_secs(2));
+        assert!((transport.beats() - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn emits_sub_beats_between_beats() {
+        let mut transport = Transport::new(60., quantized(1, 1)).sub_beats(4);
+
+        let events = transport.advance(Duration::from_secs(1));
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|event| match event {
+                TransportEvent::Beat { position, offset } => ('B', position.sub_beat, *offset),
+                TransportEvent::SubBeat { position, offset } => ('s', position.sub_beat, *offset),
+                TransportEvent::SpeedChanged { offset, .. } => ('c', 0, *offset),
+            })
+            .collect();
+
+        assert_eq!(kinds, vec![('s', 1, 0.25), ('s', 2, 0.5), ('s', 3, 0.75), ('B', 0, 1.)]);
+    }
+
+    #[test]
+    fn quantized_changes_wait_for_the_next_bar() {
+        let mut transport = Transport::new(120., quantized(1, 1)).sub_beats(1);
+
+        transport.advance(Duration::from_millis(500));
+        transport.set_speed(quantized(2, 1));
+
+        assert_eq!(transport.speed(), quantized(1, 1));
+        assert_eq!(transport.pending_speed(), Some(quantized(2, 1)));
+
+        // three more beats reach the bar at normal speed, the rest is doubled
+        let events = transport.advance(Duration::from_millis(2000));
+
+        assert!(events.contains(&TransportEvent::SpeedChanged { speed: quantized(2, 1), offset: 1.5 }));
+        assert_eq!(transport.speed(), quantized(2, 1));
+        assert_eq!(transport.beats(), 4. + 2.);
+    }
+
+    #[test]
+    fn quantized_changes_can_wait_for_the_next_beat() {
+        let mut transport = Transport::new(60., quantized(1, 1)).boundary(Boundary::Beat);
+
+        transport.advance(Duration::from_millis(250));
+        transport.set_speed(quantized(1, 2));
+
+        let events = transport.advance(Duration::from_millis(1750));
+        let change = events
+            .iter()
+            .find(|event| matches!(event, TransportEvent::SpeedChanged { .. }));
+
+        assert_eq!(change, Some(&TransportEvent::SpeedChanged { speed: quantized(1, 2), offset: 0.75 }));
+        assert_eq!(transport.beats(), 1.5);
+    }
+
+    #[test]
+    fn free_changes_apply_at_once() {
+        let mut transport = Transport::new(120., quantized(1, 1));
+
+        transport.advance(Duration::from_millis(300));
+        transport.set_speed(quantized(2, 1));
+        transport.set_speed(SpeedValue::Unquantized(1.25));
+
+        assert_eq!(transport.speed(), SpeedValue::Unquantized(1.25));
+        assert_eq!(transport.pending_speed(), None);
+    }
+
+    #[test]
+    fn quantized_changes_on_a_boundary_apply_at_once() {
+        let mut transport = Transport::new(120., quantized(1, 1));
+
+        transport.set_speed(quantized(1, 2));
+        assert_eq!(transport.speed(), quantized(1, 2));
+
+        transport.advance(Duration::from_secs(16));
+        transport.set_speed(quantized(2, 3));
+        assert_eq!(transport.speed(), quantized(2, 3));
+    }
+
+    #[test]
+    fn fake_clock_ticks() {
+        let clock = FakeClock::new();
+        let mut transport = Transport::new(120., quantized(1, 1)).sub_beats(1);
+
+        assert!(transport.tick(&clock).is_empty());
+
+        clock.advance(Duration::from_millis(400));
+        assert!(transport.tick(&clock).is_empty());
+
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(beats(&transport.tick(&clock)), vec![(0, 1)]);
+        assert!((transport.beats() - 1.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn frames_match_durations() {
+        let mut by_frames = Transport::new(93., quantized(2, 3));
+        let mut by_duration = by_frames.clone();
+
+        let mut frame_events = Vec::new();
+        for _ in 0..1000 {
+            frame_events.extend(by_frames.advance_frames(441, 44_100));
+        }
+        let duration_events = by_duration.advance(Duration::from_secs(10));
+
+        assert_eq!(beats(&frame_events), beats(&duration_events));
+        assert!((by_frames.beats() - by_duration.beats()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_sample_rate_does_not_advance() {
+        let mut transport = Transport::new(120., quantized(1, 1));
+
+        assert!(transport.advance_frames(44_100, 0).is_empty());
+        assert_eq!(transport.beats(), 0.);
+    }
+
+    #[test]
+    fn invalid_durations_do_not_advance() {
+        let mut transport = Transport::new(120., quantized(1, 1));
+
+        for seconds in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN, -1.] {
+            assert!(transport.advance_seconds(seconds).is_empty());
+        }
+        assert_eq!(transport.beats(), 0.);
+    }
+
+    #[test]
+    fn triplet_speed_lands_on_beats() {
+        // 2/3 of 90 bpm is one beat a second, rounding must not skip any
+        let mut transport = Transport::new(90., quantized(2, 3)).sub_beats(3);
+        let mut events = Vec::new();
+
+        for _ in 0..300 {
+            events.extend(transport.advance(Duration::from_millis(10)));
+        }
+
+        assert_eq!(beats(&events), vec![(0, 1), (0, 2), (0, 3)]);
+        assert_eq!(events.len(), 9);
+    }
+}
@@ -1,13 +1,15 @@
-use iced_core::{Color, Size, Font, Background, BorderRadius};
+use iced_core::{Color, Size, Font, Background, BorderRadius, Vector};
 use crate::h_slider::style::{StyleSheet, Appearance};
-use crate::color_utils::{darken, lighten};
-use iced_widget::{text, checkbox};
+use crate::color_utils::{lighten, mix, with_contrast, TonalPalette, MIN_TEXT_CONTRAST};
+use iced_widget::{button, text, checkbox, text_input};
+use lazy_static::lazy_static;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Palette {
     pub background: Color,
     pub text: Color,
-    pub primary: Color
+    pub primary: Color,
+    pub danger: Color
 }
 
 impl Palette {
@@ -22,26 +24,193 @@ impl Palette {
             0x5E as f32 / 255.0,
             0x7C as f32 / 255.0,
             0xE2 as f32 / 255.0,
+        ),
+        danger: Color::from_rgb(
+            0xC3 as f32 / 255.0,
+            0x42 as f32 / 255.0,
+            0x3F as f32 / 255.0,
+        )
+    };
+
+    pub const LIGHT: Self = Self {
+        background: Color::from_rgb(
+            0xF2 as f32 / 255.0,
+            0xF3 as f32 / 255.0,
+            0xF5 as f32 / 255.0,
+        ),
+        text: Color::from_rgb(
+            0x1C as f32 / 255.0,
+            0x1D as f32 / 255.0,
+            0x21 as f32 / 255.0,
+        ),
+        primary: Color::from_rgb(
+            0x3A as f32 / 255.0,
+            0x5B as f32 / 255.0,
+            0xC7 as f32 / 255.0,
+        ),
+        danger: Color::from_rgb(
+            0xB3 as f32 / 255.0,
+            0x26 as f32 / 255.0,
+            0x1E as f32 / 255.0,
+        )
+    };
+
+    /// Pure black and white with saturated accents, for low vision
+    pub const HIGH_CONTRAST: Self = Self {
+        background: Color::BLACK,
+        text: Color::WHITE,
+        primary: Color::from_rgb(
+            0xFF as f32 / 255.0,
+            0xD4 as f32 / 255.0,
+            0x00 as f32 / 255.0,
+        ),
+        danger: Color::from_rgb(
+            0xFF as f32 / 255.0,
+            0x50 as f32 / 255.0,
+            0x50 as f32 / 255.0,
         )
     };
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// a custom theme is a few hundred bytes, copied a handful of times a frame
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Theme {
-    Dark
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+    /// Loaded from a theme file, see [`crate::theme_file`]
+    Custom(CustomTheme)
+}
+
+/// A palette of one's own, with the slider appearance fine tuned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CustomTheme {
+    pub palette: Palette,
+    /// Generated from the background and primary colours of the palette
+    pub tones: TonalPalette,
+    /// Generated from the background and danger colours of the palette
+    pub danger_tones: TonalPalette,
+    pub h_slider: AppearanceOverrides
+}
+
+impl CustomTheme {
+    pub fn new(palette: Palette, h_slider: AppearanceOverrides) -> Self {
+        Self {
+            palette,
+            tones: TonalPalette::generate(palette.background, palette.primary),
+            danger_tones: TonalPalette::generate(palette.background, palette.danger),
+            h_slider
+        }
+    }
+}
+
+lazy_static! {
+    static ref DARK_TONES: [TonalPalette; 2] = tones_of(Palette::DARK);
+    static ref LIGHT_TONES: [TonalPalette; 2] = tones_of(Palette::LIGHT);
+    static ref HIGH_CONTRAST_TONES: [TonalPalette; 2] = tones_of(Palette::HIGH_CONTRAST).map(high_contrast);
+}
+
+/// The primary and danger tones of a built-in palette
+fn tones_of(palette: Palette) -> [TonalPalette; 2] {
+    [
+        TonalPalette::generate(palette.background, palette.primary),
+        TonalPalette::generate(palette.background, palette.danger),
+    ]
+}
+
+/// Larger surface steps, and borders, bold marks and secondary text as
+/// bright as the text
+fn high_contrast(tones: TonalPalette) -> TonalPalette {
+    let background = tones.surfaces[0];
+
+    TonalPalette {
+        surfaces: [0.0, 0.1, 0.2, 0.3].map(|amount| lighten(background, amount)),
+        border: tones.text,
+        text_muted: tones.text,
+        mark_bold: tones.text,
+        ..tones
+    }
+}
+
+/// Replacements for the fields of an h_slider [`Appearance`] derived from the
+/// palette, the ones left to `None` are kept
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AppearanceOverrides {
+    pub background_color: Option<Color>,
+    pub rail_color: Option<Color>,
+    pub fill_color: Option<Color>,
+    pub handle_color: Option<Color>,
+    pub mark_color_normal: Option<Color>,
+    pub mark_color_bold: Option<Color>,
+    pub mark_width: Option<f32>,
+    pub text_mark_color: Option<Color>,
+    pub text_mark_font: Option<Font>,
+    pub text_mark_size: Option<f32>
+}
+
+impl AppearanceOverrides {
+    pub fn apply(&self, appearance: Appearance) -> Appearance {
+        Appearance {
+            background_color: self.background_color.unwrap_or(appearance.background_color),
+            rail_color: self.rail_color.unwrap_or(appearance.rail_color),
+            fill_color: self.fill_color.unwrap_or(appearance.fill_color),
+            handle_color: self.handle_color.unwrap_or(appearance.handle_color),
+            mark_color_normal: self.mark_color_normal.unwrap_or(appearance.mark_color_normal),
+            mark_color_bold: self.mark_color_bold.unwrap_or(appearance.mark_color_bold),
+            mark_width: self.mark_width.unwrap_or(appearance.mark_width),
+            text_mark_color: self.text_mark_color.unwrap_or(appearance.text_mark_color),
+            text_mark_font: self.text_mark_font.unwrap_or(appearance.text_mark_font),
+            text_mark_size: self.text_mark_size.unwrap_or(appearance.text_mark_size),
+        }
+    }
 }
 
 impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
     pub fn palette(self) -> Palette {
         match self {
             Self::Dark => Palette::DARK,
+            Self::Light => Palette::LIGHT,
+            Self::HighContrast => Palette::HIGH_CONTRAST,
+            Self::Custom(custom) => custom.palette,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::HighContrast => "High contrast",
+            Self::Custom(_) => "Custom",
+        }
+    }
+
+    /// Surfaces, accents and marks, every widget is drawn with them
+    pub fn tones(self) -> TonalPalette {
+        match self {
+            Self::Dark => DARK_TONES[0],
+            Self::Light => LIGHT_TONES[0],
+            Self::HighContrast => HIGH_CONTRAST_TONES[0],
+            Self::Custom(custom) => custom.tones,
+        }
+    }
+
+    /// The tones of the danger colour, for errors and destructive actions
+    pub fn danger_tones(self) -> TonalPalette {
+        match self {
+            Self::Dark => DARK_TONES[1],
+            Self::Light => LIGHT_TONES[1],
+            Self::HighContrast => HIGH_CONTRAST_TONES[1],
+            Self::Custom(custom) => custom.danger_tones,
         }
     }
-}
 
-impl Default for Theme {
-    fn default() -> Self {
-        Self::Dark
+    /// The danger colour towards the text, as long as it stays readable
+    fn error_color(self) -> Color {
+        with_contrast(self.danger_tones().accent_hovered, self.palette().background, MIN_TEXT_CONTRAST)
     }
 }
 
@@ -49,15 +218,16 @@ impl Default for Theme {
  * h_slider
  */
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum HSliderStyleType {
-    Classic
+    #[default]
+    Classic,
+    /// Waiting for a MIDI controller to bind to
+    Learning
 }
 
-impl Default for HSliderStyleType {
-    fn default() -> Self {
-        Self::Classic
-    }
+impl HSliderStyleType {
+    pub const ALL: [HSliderStyleType; 2] = [HSliderStyleType::Classic, HSliderStyleType::Learning];
 }
 
 pub const DEFAULT_TEXT_MARKER_HEIGHT: f32 = 18.0;
@@ -65,35 +235,72 @@ pub const DEFAULT_RAIL_HEIGHT: f32 = 8.0;
 pub const DEFAULT_HANDLE_SIZE: Size = Size::new(24., 14.);
 pub const RAIL_HANDLE_MARGIN: f32 = 3.;
 
+// v_slider shares the h_slider StyleSheet
+pub const DEFAULT_TEXT_MARKER_WIDTH: f32 = 24.0;
+pub const DEFAULT_RAIL_WIDTH: f32 = 8.0;
+pub const DEFAULT_V_HANDLE_SIZE: Size = Size::new(14., 24.);
+
 impl StyleSheet for Theme {
     type Style = HSliderStyleType;
 
     fn active(&self, style: Self::Style) -> Appearance {
-        let palette = self.palette();
+        let tones = self.tones();
 
         let appearance = Appearance {
-            background_color: darken(palette.background, 0.3),
-            rail_color: darken(palette.background, 0.1),
-            handle_color: palette.primary,
-            mark_color_normal: palette.background,
-            mark_color_bold: lighten(palette.background, 0.2),
+            background_color: tones.surfaces[3],
+            rail_color: tones.surfaces[1],
+            fill_color: tones.accent_pressed,
+            handle_color: tones.accent,
+            mark_color_normal: tones.mark_normal,
+            mark_color_bold: tones.mark_bold,
             mark_width: 2.0,
-            text_mark_color: Color::WHITE,
+            text_mark_color: self.palette().text,
             text_mark_font: Font::default(),
             text_mark_size: 12.
         };
 
+        let appearance = match self {
+            Theme::Custom(custom) => custom.h_slider.apply(appearance),
+            _ => appearance,
+        };
+
         match style {
             HSliderStyleType::Classic => appearance,
+            HSliderStyleType::Learning => {
+                let danger_tones = self.danger_tones();
+
+                Appearance {
+                    fill_color: danger_tones.accent_pressed,
+                    handle_color: danger_tones.accent,
+                    ..appearance
+                }
+            },
         }
     }
 
     fn hovered(&self, style: Self::Style) -> Appearance {
-        let palette = self.palette();
+        match style {
+            HSliderStyleType::Classic => Appearance {
+                handle_color: self.tones().accent_hovered,
+                ..self.active(style)
+            },
+            HSliderStyleType::Learning => self.active(style),
+        }
+    }
 
-        Appearance {
-            handle_color: lighten(palette.primary, 0.1),
-            ..self.active(style)
+    /// The rail steps in as well, focus shows without hovering
+    fn focused(&self, style: Self::Style) -> Appearance {
+        match style {
+            HSliderStyleType::Classic => {
+                let tones = self.tones();
+
+                Appearance {
+                    handle_color: tones.accent_hovered,
+                    rail_color: tones.surfaces[2],
+                    ..self.active(style)
+                }
+            },
+            HSliderStyleType::Learning => self.active(style),
         }
     }
 }
@@ -102,15 +309,15 @@ impl StyleSheet for Theme {
  * text
  */
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum TextStyle {
-    Default
+    #[default]
+    Default,
+    Error
 }
 
-impl Default for TextStyle {
-    fn default() -> Self {
-        Self::Default
-    }
+impl TextStyle {
+    pub const ALL: [TextStyle; 2] = [TextStyle::Default, TextStyle::Error];
 }
 
 impl text::StyleSheet for Theme {
@@ -118,7 +325,10 @@ impl text::StyleSheet for Theme {
 
     fn appearance(&self, style: Self::Style) -> text::Appearance {
         match style {
-            TextStyle::Default => Default::default()
+            TextStyle::Default => Default::default(),
+            TextStyle::Error => text::Appearance {
+                color: Some(self.error_color())
+            }
         }
     }
 }
@@ -127,44 +337,185 @@ impl text::StyleSheet for Theme {
  * checkbox
  */
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum CheckboxStyle {
+    #[default]
     Default
 }
 
-impl Default for CheckboxStyle {
-    fn default() -> Self {
-        Self::Default
-    }
+impl CheckboxStyle {
+    pub const ALL: [CheckboxStyle; 1] = [CheckboxStyle::Default];
 }
 
 impl checkbox::StyleSheet for Theme {
     type Style = CheckboxStyle;
 
-    fn hovered(&self, _style: &Self::Style, _is_checked: bool) -> checkbox::Appearance {
-        let palette = self.palette();
-
+    fn hovered(&self, style: &Self::Style, is_checked: bool) -> checkbox::Appearance {
         checkbox::Appearance {
-            background: Background::Color(palette.background),
-            icon_color: lighten(palette.primary, 0.1),
-            border_radius: BorderRadius::from(0.),
-            border_width: 2.,
-            border_color: lighten(palette.background, 0.2),
-            text_color: Some(palette.text),
+            icon_color: self.tones().accent_hovered,
+            text_color: Some(self.palette().text),
+            ..checkbox::StyleSheet::active(self, style, is_checked)
         }
     }
 
     fn active(&self, _style: &Self::Style, _is_checked: bool) -> checkbox::Appearance {
-        let palette = self.palette();
+        let tones = self.tones();
 
         checkbox::Appearance {
-            background: Background::Color(palette.background),
-            icon_color: palette.primary,
+            background: Background::Color(tones.surfaces[0]),
+            icon_color: tones.accent,
             border_radius: BorderRadius::from(0.),
             border_width: 2.,
-            border_color: lighten(palette.background, 0.2),
-            text_color: Some(darken(palette.text, 0.2)),
+            border_color: tones.border,
+            text_color: Some(tones.text_muted),
         }
     }
 }
 
+/**
+ * text_input
+ */
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum TextInputStyle {
+    #[default]
+    Default,
+    Error
+}
+
+impl TextInputStyle {
+    pub const ALL: [TextInputStyle; 2] = [TextInputStyle::Default, TextInputStyle::Error];
+}
+
+impl text_input::StyleSheet for Theme {
+    type Style = TextInputStyle;
+
+    fn active(&self, style: &Self::Style) -> text_input::Appearance {
+        let tones = self.tones();
+
+        text_input::Appearance {
+            background: Background::Color(tones.surfaces[1]),
+            border_radius: BorderRadius::from(0.),
+            border_width: 1.,
+            border_color: match style {
+                TextInputStyle::Default => tones.border,
+                TextInputStyle::Error => self.danger_tones().accent,
+            },
+            icon_color: self.palette().text,
+        }
+    }
+
+    fn focused(&self, style: &Self::Style) -> text_input::Appearance {
+        text_input::Appearance {
+            border_color: match style {
+                TextInputStyle::Default => self.tones().accent,
+                TextInputStyle::Error => self.danger_tones().accent,
+            },
+            ..text_input::StyleSheet::active(self, style)
+        }
+    }
+
+    fn placeholder_color(&self, _style: &Self::Style) -> Color {
+        let tones = self.tones();
+
+        with_contrast(tones.text_muted, tones.surfaces[1], MIN_TEXT_CONTRAST)
+    }
+
+    fn value_color(&self, style: &Self::Style) -> Color {
+        match style {
+            TextInputStyle::Default => self.palette().text,
+            TextInputStyle::Error => self.error_color(),
+        }
+    }
+
+    fn disabled_color(&self, _style: &Self::Style) -> Color {
+        self.tones().disabled_text
+    }
+
+    /// Half way to the input background, the selected text stays readable
+    fn selection_color(&self, _style: &Self::Style) -> Color {
+        let tones = self.tones();
+
+        mix(tones.accent, tones.surfaces[1], 0.5)
+    }
+
+    fn disabled(&self, style: &Self::Style) -> text_input::Appearance {
+        text_input::Appearance {
+            background: Background::Color(self.tones().disabled),
+            ..text_input::StyleSheet::active(self, style)
+        }
+    }
+}
+
+/**
+ * button
+ */
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ButtonStyle {
+    #[default]
+    Default,
+    Selected,
+    Danger
+}
+
+impl ButtonStyle {
+    pub const ALL: [ButtonStyle; 3] = [ButtonStyle::Default, ButtonStyle::Selected, ButtonStyle::Danger];
+}
+
+impl Theme {
+    /// The fill of a button and the text on it, pressed or not
+    fn button_fill(self, style: ButtonStyle, is_pressed: bool) -> (Color, Color) {
+        let (tones, text) = match style {
+            ButtonStyle::Default => {
+                let tones = self.tones();
+                let fill = if is_pressed { tones.surfaces[3] } else { tones.surfaces[2] };
+
+                return (fill, with_contrast(self.palette().text, fill, MIN_TEXT_CONTRAST));
+            },
+            ButtonStyle::Selected => (self.tones(), self.tones().on_accent),
+            ButtonStyle::Danger => (self.danger_tones(), self.danger_tones().on_accent),
+        };
+
+        let fill = if is_pressed { tones.accent_pressed } else { tones.accent };
+
+        (fill, with_contrast(text, fill, MIN_TEXT_CONTRAST))
+    }
+}
+
+impl button::StyleSheet for Theme {
+    type Style = ButtonStyle;
+
+    fn active(&self, style: &Self::Style) -> button::Appearance {
+        let (background, text_color) = self.button_fill(*style, false);
+
+        button::Appearance {
+            shadow_offset: Vector::default(),
+            background: Some(Background::Color(background)),
+            border_radius: BorderRadius::from(0.),
+            border_width: 1.,
+            border_color: self.tones().border,
+            text_color,
+        }
+    }
+
+    fn hovered(&self, style: &Self::Style) -> button::Appearance {
+        button::Appearance {
+            border_color: match style {
+                ButtonStyle::Danger => self.danger_tones().accent,
+                _ => self.tones().accent,
+            },
+            ..button::StyleSheet::active(self, style)
+        }
+    }
+
+    fn pressed(&self, style: &Self::Style) -> button::Appearance {
+        let (background, text_color) = self.button_fill(*style, true);
+
+        button::Appearance {
+            background: Some(Background::Color(background)),
+            text_color,
+            ..button::StyleSheet::hovered(self, style)
+        }
+    }
+}
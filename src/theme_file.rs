@@ -0,0 +1,329 @@
+//! Custom themes read from a file, and reloaded as it changes
+//!
+//! A theme file gives the colours of a [`Palette`] and, under `h_slider`,
+//! overrides for any field of the slider [`Appearance`]. Colours are
+//! `#rgb`, `#rrggbb` or `#rrggbbaa` strings:
+//!
+//! ```toml
+//! background = "#1d1f21"
+//! text = "#e0e0e0"
+//! primary = "#f0a030"
+//!
+//! [h_slider]
+//! rail_color = "#303336"
+//! text_mark_size = 14
+//! text_mark_font = "monospace"
+//! ```
+//!
+//! The rest of the slider colours are derived from the background and the
+//! primary colour, see [`TonalPalette`]. `text` is optional too and defaults
+//! to a colour readable on every surface, `danger` defaults to the one of the
+//! dark theme.
+//!
+//! [`Appearance`]: crate::h_slider::style::Appearance
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use iced_core::{font, Color, Font};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+use crate::color_utils::TonalPalette;
+use crate::theme::{AppearanceOverrides, CustomTheme, Palette};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    background: HexColor,
+    text: Option<HexColor>,
+    primary: HexColor,
+    danger: Option<HexColor>,
+    #[serde(default)]
+    h_slider: AppearanceFile,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AppearanceFile {
+    background_color: Option<HexColor>,
+    rail_color: Option<HexColor>,
+    fill_color: Option<HexColor>,
+    handle_color: Option<HexColor>,
+    mark_color_normal: Option<HexColor>,
+    mark_color_bold: Option<HexColor>,
+    mark_width: Option<f32>,
+    text_mark_color: Option<HexColor>,
+    text_mark_font: Option<FontFamily>,
+    text_mark_size: Option<f32>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum FontFamily {
+    SansSerif,
+    Serif,
+    Monospace,
+}
+
+struct HexColor(Color);
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        parse_hex(&text)
+            .map(HexColor)
+            .ok_or_else(|| de::Error::custom(format!("\"{}\" is not a #rrggbb colour", text)))
+    }
+}
+
+fn parse_hex(text: &str) -> Option<Color> {
+    let digits = text.strip_prefix('#')?;
+
+    if !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let channel = |index: usize, width: usize| {
+        let value = u8::from_str_radix(&digits[index * width..(index + 1) * width], 16).ok()?;
+        // #rgb is #rrggbb with every digit doubled
+        Some(if width == 1 { value * 0x11 } else { value })
+    };
+
+    let (width, alpha) = match digits.len() {
+        3 => (1, None),
+        6 => (2, None),
+        8 => (2, Some(channel(3, 2)?)),
+        _ => return None,
+    };
+
+    Some(Color::from_rgba8(
+        channel(0, width)?,
+        channel(1, width)?,
+        channel(2, width)?,
+        alpha.map_or(1., |alpha| alpha as f32 / 255.),
+    ))
+}
+
+impl FontFamily {
+    fn font(self) -> Font {
+        match self {
+            FontFamily::SansSerif => Font::DEFAULT,
+            FontFamily::Serif => Font {
+                family: font::Family::Serif,
+                ..Font::DEFAULT
+            },
+            FontFamily::Monospace => Font::MONOSPACE,
+        }
+    }
+}
+
+/// Reads a theme from a `.toml` or `.json` file
+pub fn load(path: impl AsRef<Path>) -> Result<CustomTheme, ThemeFileError> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path).map_err(ThemeFileError::Io)?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => from_toml(&contents),
+        Some("json") => from_json(&contents),
+        _ => Err(ThemeFileError::UnknownFormat),
+    }
+}
+
+pub fn from_toml(contents: &str) -> Result<CustomTheme, ThemeFileError> {
+    Ok(from_file(toml::from_str(contents).map_err(ThemeFileError::Toml)?))
+}
+
+pub fn from_json(contents: &str) -> Result<CustomTheme, ThemeFileError> {
+    Ok(from_file(serde_json::from_str(contents).map_err(ThemeFileError::Json)?))
+}
+
+fn from_file(file: ThemeFile) -> CustomTheme {
+    let h_slider = file.h_slider;
+    let color = |color: Option<HexColor>| color.map(|HexColor(color)| color);
+
+    let background = file.background.0;
+    let primary = file.primary.0;
+
+    let palette = Palette {
+        background,
+        text: color(file.text).unwrap_or_else(|| TonalPalette::generate(background, primary).text),
+        primary,
+        danger: color(file.danger).unwrap_or(Palette::DARK.danger),
+    };
+
+    CustomTheme::new(
+        palette,
+        AppearanceOverrides {
+            background_color: color(h_slider.background_color),
+            rail_color: color(h_slider.rail_color),
+            fill_color: color(h_slider.fill_color),
+            handle_color: color(h_slider.handle_color),
+            mark_color_normal: color(h_slider.mark_color_normal),
+            mark_color_bold: color(h_slider.mark_color_bold),
+            mark_width: h_slider.mark_width,
+            text_mark_color: color(h_slider.text_mark_color),
+            text_mark_font: h_slider.text_mark_font.map(FontFamily::font),
+            text_mark_size: h_slider.text_mark_size,
+        },
+    )
+}
+
+/// Loads a theme file again whenever it changes
+pub struct ThemeWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl ThemeWatcher {
+    /// Hands every reload of `path` to `on_load`, failed ones included
+    ///
+    /// The directory is watched rather than the file, editors often save by
+    /// replacing the file with a new one.
+    pub fn spawn<F>(path: PathBuf, on_load: F) -> notify::Result<Self>
+    where
+        F: Fn(Result<CustomTheme, ThemeFileError>) + Send + 'static,
+    {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        let file_name = path.file_name().map(|file_name| file_name.to_owned());
+
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) => {
+                    let is_theme_file = event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == file_name.as_deref());
+
+                    if is_theme_file && (event.kind.is_create() || event.kind.is_modify()) {
+                        on_load(load(&path));
+                    }
+                }
+                Err(error) => eprintln!("Theme watcher: {}", error),
+            }
+        })?;
+
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeFileError {
+    Io(io::Error),
+    UnknownFormat,
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for ThemeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeFileError::Io(error) => write!(f, "{}", error),
+            ThemeFileError::UnknownFormat => write!(f, "expected a .toml or .json file"),
+            ThemeFileError::Toml(error) => write!(f, "{}", error),
+            ThemeFileError::Json(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ThemeFileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_hex_colours() {
+        assert_eq!(parse_hex("#ff8000"), Some(Color::from_rgb8(0xFF, 0x80, 0x00)));
+        assert_eq!(parse_hex("#f80"), Some(Color::from_rgb8(0xFF, 0x88, 0x00)));
+        assert_eq!(parse_hex("#00000080"), Some(Color::from_rgba8(0, 0, 0, 128. / 255.)));
+
+        for invalid in ["ff8000", "#ff80", "#gg8000", "#ff800", "#+f8000"] {
+            assert_eq!(parse_hex(invalid), None, "{:?} was parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn loads_toml_and_json() {
+        let theme = from_toml(
+            r##"
+            background = "#101010"
+            text = "#f0f0f0"
+            primary = "#f0a030"
+
+            [h_slider]
+            rail_color = "#303030"
+            text_mark_size = 14
+            text_mark_font = "monospace"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.palette.primary, Color::from_rgb8(0xF0, 0xA0, 0x30));
+        assert_eq!(theme.palette.danger, Palette::DARK.danger);
+        assert_eq!(theme.h_slider.rail_color, Some(Color::from_rgb8(0x30, 0x30, 0x30)));
+        assert_eq!(theme.h_slider.text_mark_size, Some(14.));
+        assert_eq!(theme.h_slider.text_mark_font, Some(Font::MONOSPACE));
+        assert_eq!(theme.h_slider.fill_color, None);
+
+        let json = from_json(
+            r##"{ "background": "#fff", "text": "#000", "primary": "#00f", "danger": "#f00" }"##,
+        )
+        .unwrap();
+
+        assert_eq!(json.palette.danger, Color::from_rgb8(0xFF, 0, 0));
+        assert_eq!(json.h_slider, AppearanceOverrides::default());
+
+        let accent_only = from_toml("background = \"#2a1a3a\"\nprimary = \"#ff70a0\"").unwrap();
+        assert_eq!(accent_only.palette.text, accent_only.tones.text);
+    }
+
+    #[test]
+    fn reports_invalid_files() {
+        let error = from_toml("background = \"#101010\"\ntext = \"white\"\nprimary = \"#fff\"")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("\"white\" is not a #rrggbb colour"), "{}", error);
+
+        assert!(matches!(
+            from_toml("background = \"#000\"\ntext = \"#fff\"\nprimary = \"#fff\"\nrail = \"#111\""),
+            Err(ThemeFileError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn reloads_on_change() {
+        let dir = std::env::temp_dir().join(format!("theme-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("theme.toml");
+        fs::write(&path, "background = \"#000\"\ntext = \"#fff\"\nprimary = \"#f00\"").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = ThemeWatcher::spawn(path.clone(), move |theme| {
+            let _ = sender.send(theme.map(|theme| theme.palette.primary).map_err(|error| error.to_string()));
+        })
+        .unwrap();
+
+        fs::write(&path, "background = \"#000\"\ntext = \"#fff\"\nprimary = \"#00f\"").unwrap();
+
+        // a write can show up as several events, some of a half written file
+        let blue = Ok(Color::from_rgb8(0, 0, 0xFF));
+        let reloaded = std::iter::from_fn(|| receiver.recv_timeout(Duration::from_secs(5)).ok())
+            .any(|primary| primary == blue);
+
+        assert!(reloaded);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
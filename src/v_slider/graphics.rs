@@ -0,0 +1,215 @@
+use iced_core::{Size, Rectangle, Vector, Point, alignment::{Horizontal, Vertical}, text::{Shaping, LineHeight}};
+use iced_widget::canvas::{
+    path::Path, Frame, Fill, Text, Style
+};
+
+use crate::h_slider::style::Appearance;
+use crate::h_slider::normal::Normal;
+use crate::h_slider::Marker;
+use crate::theme::RAIL_HANDLE_MARGIN;
+use crate::speed::MarkWeight;
+
+pub fn draw_marks(
+    frame: &mut Frame,
+    size: Size,
+    appearance: Appearance,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32,
+    markers: Option<&[Marker]>,
+) {
+    if let Some(markers) = markers {
+        let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_width, rail_width);
+        let marks_bounds = Rectangle {
+            x: rail_bounds.x,
+            y: rail_bounds.y - appearance.mark_width * 0.5,
+            width: rail_bounds.width,
+            height: rail_bounds.height
+        };
+
+        for (normal, _text, weight) in markers {
+            if let Some(weight) = weight {
+                let mark_bounds = Rectangle {
+                    x: marks_bounds.x,
+                    y: marks_bounds.y + normal.scale_inv(marks_bounds.height),
+                    width: marks_bounds.width,
+                    height: appearance.mark_width
+                };
+
+                let mark = Path::rectangle(mark_bounds.position(), mark_bounds.size());
+
+                let mark_fill_color = match weight {
+                    MarkWeight::Normal => appearance.mark_color_normal,
+                    MarkWeight::Bold => appearance.mark_color_bold
+                };
+
+                let mark_fill = Fill {
+                    style: Style::Solid(mark_fill_color),
+                    ..Fill::default()
+                };
+
+                frame.fill(&mark, mark_fill);
+            }
+        }
+    }
+}
+
+pub fn draw_text_marks(
+    frame: &mut Frame,
+    size: Size,
+    appearance: Appearance,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32,
+    markers: Option<&[Marker]>
+) {
+    if let Some(markers) = markers {
+        let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_width, rail_width);
+
+        for (normal, text, _) in markers {
+            let mark_offset: f32 = rail_bounds.y + normal.scale_inv(rail_bounds.height);
+
+            if let Some(text) = text {
+                let text_mark = Text {
+                    content: text.to_string(),
+                    position: Point {
+                        x: text_mark_width - RAIL_HANDLE_MARGIN,
+                        y: mark_offset
+                    },
+                    color: appearance.text_mark_color,
+                    size: appearance.text_mark_size,
+                    font: appearance.text_mark_font,
+                    horizontal_alignment: Horizontal::Right,
+                    vertical_alignment: Vertical::Center,
+                    line_height: LineHeight::default(),
+                    shaping: Shaping::default()
+                };
+
+                frame.fill_text(text_mark);
+            }
+        }
+    }
+}
+
+/// Text marks sit on the left of the rail and the handle on its right, the
+/// maximum value is at the top of the rail
+fn get_frame_rail_bounds (
+    size: Size,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) -> Rectangle {
+    Rectangle {
+        x: text_mark_width,
+        y: handle_size.height * 0.5,
+        width: rail_width,
+        height: size.height - handle_size.height
+    }
+}
+
+pub fn get_text_and_rail_bounds (
+    bounds: Rectangle,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) -> Rectangle {
+    let frame_rail_bounds = get_frame_rail_bounds(bounds.size(), handle_size, text_mark_width, rail_width);
+    Rectangle {
+        x: bounds.x,
+        y: bounds.y + frame_rail_bounds.y,
+        width: frame_rail_bounds.width + text_mark_width,
+        height: frame_rail_bounds.height
+    }
+}
+
+pub fn draw_slider_rail(
+    frame: &mut Frame,
+    size: Size,
+    appearance: Appearance,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) {
+    let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_width, rail_width);
+
+    let rail = Path::rectangle(rail_bounds.position(), rail_bounds.size());
+    let rail_fill = Fill {
+        style: Style::Solid(appearance.rail_color),
+        ..Fill::default()
+    };
+
+    frame.fill(
+        &rail,
+        rail_fill
+    );
+}
+
+pub fn get_handle_position(
+    size: Size,
+    value: Normal,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) -> Vector {
+    let rail_bounds = get_frame_rail_bounds(size, handle_size, text_mark_width, rail_width);
+
+    let handle_offset = value
+        .scale_inv(rail_bounds.height);
+
+    Vector {
+        x: rail_bounds.x + rail_bounds.width + RAIL_HANDLE_MARGIN,
+        y: rail_bounds.y + handle_offset
+    }
+}
+
+pub fn get_handle_bounds(
+    bounds: Rectangle,
+    value: Normal,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) -> Rectangle {
+    let handle_position = get_handle_position(bounds.size(), value, handle_size, text_mark_width, rail_width);
+
+    Rectangle {
+        x: bounds.x + handle_position.x,
+        y: bounds.y + handle_position.y - handle_size.height * 0.5,
+        width: handle_size.width,
+        height: handle_size.height
+    }
+}
+
+pub fn draw_handle(
+    frame: &mut Frame,
+    size: Size,
+    value: Normal,
+    appearance: Appearance,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32
+) {
+    let handle_position = get_handle_position(size, value, handle_size, text_mark_width, rail_width);
+
+    frame.with_save(|frame| {
+        frame.translate(Vector {
+            x: handle_position.x,
+            y: handle_position.y
+        });
+
+        let handle =  Path::new(|f| {
+            f.line_to(Point { x: handle_size.width, y: handle_size.height * -0.5 });
+            f.line_to(Point { x: handle_size.width, y: handle_size.height * 0.5 });
+            f.line_to(Point { x: 0., y: 0. });
+        });
+
+        let handle_fill = Fill {
+            style: Style::Solid(appearance.handle_color),
+            ..Fill::default()
+        };
+
+        frame.fill(
+            &handle,
+            handle_fill
+        );
+    });
+}
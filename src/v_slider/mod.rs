@@ -0,0 +1,335 @@
+mod graphics;
+
+use graphics::*;
+
+use crate::h_slider::engine::{Focusable, Orientation, SliderBounds, SliderCore, State};
+use crate::h_slider::normal::{Normal, NormalParam};
+use crate::h_slider::taper::TaperedRange;
+use crate::h_slider::style::StyleSheet;
+use crate::h_slider::Marker;
+use crate::theme::{DEFAULT_RAIL_WIDTH, DEFAULT_TEXT_MARKER_WIDTH, DEFAULT_V_HANDLE_SIZE, RAIL_HANDLE_MARGIN};
+
+use iced_core::{
+    event, keyboard, layout, mouse::{self, Cursor}, Vector,
+    Clipboard, Element, Event, Layout, Length, Point, Rectangle, Shell, Size,
+    Renderer as _,
+};
+
+// most generic iced renderer
+use iced::Renderer;
+
+use iced_widget::canvas::{self, Frame, Cache};
+use iced::advanced::{
+    renderer,
+    widget::{tree, Operation, Tree, Widget},
+};
+
+/// A vertical fader with its text marks on the left of the rail, driven by
+/// the same engine as [`HSlider`](crate::h_slider::HSlider)
+#[allow(missing_debug_implementations)]
+pub struct VSlider<'a, Message, Theme>
+where
+    Theme: StyleSheet,
+{
+    core: SliderCore<'a, Message>,
+    width: Length,
+    height: Length,
+    style: <Theme as StyleSheet>::Style,
+    geometry_cache: Cache,
+    markers: Option<&'a [Marker]>,
+    handle_size: Size,
+    text_mark_width: f32,
+    rail_width: f32,
+}
+
+impl<'a, Message, Theme> VSlider<'a, Message, Theme>
+where
+    Message: Clone,
+    Theme: StyleSheet,
+{
+    pub fn new<F>(normal_param: NormalParam, on_change: F) -> Self
+    where
+        F: 'static + Fn(Normal, Option<usize>) -> Message,
+    {
+        VSlider {
+            core: SliderCore::new(normal_param, on_change, Orientation::Vertical),
+            width: Length::Shrink,
+            height: Length::Fill,
+            style: Default::default(),
+            geometry_cache: canvas::Cache::default(),
+            markers: None,
+            handle_size: DEFAULT_V_HANDLE_SIZE,
+            text_mark_width: DEFAULT_TEXT_MARKER_WIDTH,
+            rail_width: DEFAULT_RAIL_WIDTH,
+        }
+    }
+
+    /// A slider over the values of `range`, stepped ranges snap to each of
+    /// their values
+    pub fn with_range<R, F>(range: R, value: R::Value, default: R::Value, on_change: F) -> Self
+    where
+        R: 'static + TaperedRange,
+        F: 'static + Fn(R::Value) -> Message,
+    {
+        let normal_param = range.normal_param(value, default);
+        let snap_normals = range.snap_to(default);
+
+        Self::new(normal_param, move |normal, _| on_change(range.unmap_to_value(normal)))
+            .snap_to_normals(snap_normals)
+    }
+
+    /// Sets the message published when a drag of the handle starts
+    pub fn on_grab(mut self, on_grab: Message) -> Self {
+        self.core.on_grab = Some(on_grab);
+        self
+    }
+
+    /// Sets the message published when a drag of the handle ends, a whole
+    /// drag can then be handled as a single change
+    pub fn on_release(mut self, on_release: Message) -> Self {
+        self.core.on_release = Some(on_release);
+        self
+    }
+
+    /// Sets the message published on a right click, such as to start a
+    /// MIDI learn
+    pub fn on_right_click(mut self, on_right_click: Message) -> Self {
+        self.core.on_right_click = Some(on_right_click);
+        self
+    }
+
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn height(mut self, height: Length) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn style(mut self, style: impl Into<<Theme as StyleSheet>::Style>) -> Self {
+        self.style = style.into();
+        self
+    }
+
+    pub fn modifier_keys(mut self, modifier_keys: keyboard::Modifiers) -> Self {
+        self.core.modifier_keys = modifier_keys;
+        self
+    }
+
+    pub fn scalar(mut self, scalar: f32) -> Self {
+        self.core.scalar = scalar;
+        self
+    }
+
+    pub fn wheel_scalar(mut self, wheel_scalar: f32) -> Self {
+        self.core.wheel_scalar = wheel_scalar;
+        self
+    }
+
+    pub fn modifier_scalar(mut self, scalar: f32) -> Self {
+        self.core.modifier_scalar = scalar;
+        self
+    }
+
+    pub fn keyboard_step(mut self, keyboard_step: f32) -> Self {
+        self.core.keyboard_step = keyboard_step;
+        self
+    }
+
+    pub fn page_step(mut self, page_step: f32) -> Self {
+        self.core.page_step = page_step;
+        self
+    }
+
+    pub fn snap_to_normals(mut self, snap_normals: Option<(Vec<f32>, usize)>) -> Self {
+        self.core.snap_normals = snap_normals;
+        self
+    }
+
+    pub fn markers(mut self, markers: Option<&'a [Marker]>) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    pub fn handle_size(mut self, handle_size: Size) -> Self {
+        self.handle_size = handle_size;
+        self
+    }
+
+    pub fn text_mark_width(mut self, text_mark_width: f32) -> Self {
+        self.text_mark_width = text_mark_width;
+        self
+    }
+
+    pub fn rail_width(mut self, rail_width: f32) -> Self {
+        self.rail_width = rail_width;
+        self
+    }
+
+    /// Width taken by the text marks, the rail and the handle
+    fn intrinsic_width(&self) -> f32 {
+        self.text_mark_width + self.rail_width + RAIL_HANDLE_MARGIN + self.handle_size.width
+    }
+}
+
+impl<'a, Message, Theme> Widget<Message, Renderer<Theme>> for VSlider<'a, Message, Theme>
+where
+    Message: Clone,
+    Theme: StyleSheet,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::new(self.core.normal_param))
+    }
+
+    fn width(&self) -> Length {
+        self.width
+    }
+
+    fn height(&self) -> Length {
+        self.height
+    }
+
+    fn layout(&self, _renderer: &Renderer<Theme>, limits: &layout::Limits) -> layout::Node {
+        let limits = limits.width(self.width).height(self.height);
+
+        let size = limits.resolve(Size::new(self.intrinsic_width(), 0.0));
+
+        layout::Node::new(size)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        _layout: Layout<'_>,
+        _renderer: &Renderer<Theme>,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        let state = tree.state.downcast_mut::<State>();
+
+        operation.focusable(state, None);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer<Theme>,
+        _clipboard: &mut dyn Clipboard,
+        messages: &mut Shell<'_, Message>,
+        _: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
+
+        let slider_bounds = SliderBounds {
+            bounds,
+            rail: get_text_and_rail_bounds(
+                bounds,
+                self.handle_size,
+                self.text_mark_width,
+                self.rail_width,
+            ),
+            handle: get_handle_bounds(
+                bounds,
+                self.core.normal_param.value,
+                self.handle_size,
+                self.text_mark_width,
+                self.rail_width,
+            ),
+        };
+
+        self.core.on_event(state, event, slider_bounds, cursor, messages)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer<Theme>,
+        theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let size = bounds.size();
+        let is_mouse_over = cursor.position_over(bounds).is_some();
+        let is_focused = tree.state.downcast_ref::<State>().is_focused();
+
+        let appearance = if is_focused {
+            theme.focused(self.style)
+        } else if is_mouse_over {
+            theme.hovered(self.style)
+        } else {
+            theme.active(self.style)
+        };
+
+        let static_primitives = self.geometry_cache.draw(renderer, size, |frame| {
+            draw_text_marks(frame, size, appearance, self.handle_size, self.text_mark_width, self.rail_width, self.markers);
+            draw_slider_rail(frame, size, appearance, self.handle_size, self.text_mark_width, self.rail_width);
+            draw_marks(frame, size, appearance, self.handle_size, self.text_mark_width, self.rail_width, self.markers);
+        });
+
+        // frame for dynamic primitives
+        let mut dynamic_frame = Frame::new(renderer, size);
+
+        draw_handle(
+            &mut dynamic_frame,
+            size,
+            self.core.normal_param.value,
+            appearance,
+            self.handle_size,
+            self.text_mark_width,
+            self.rail_width
+        );
+
+        renderer.with_translation(
+            Vector::new(bounds.x, bounds.y),
+            |renderer| {
+                use iced::advanced::graphics::geometry::Renderer as _;
+
+                renderer.draw(vec![
+                    static_primitives,
+                    dynamic_frame.into_geometry()
+                ]);
+            },
+        );
+    }
+
+    fn diff(&self, _tree: &mut Tree) {}
+
+    fn mouse_interaction(
+        &self,
+        _state: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer<Theme>,
+    ) -> mouse::Interaction {
+        let bounds = layout.bounds();
+        let cursor_position = cursor.position().unwrap_or(Point::ORIGIN);
+        if bounds.contains(cursor_position) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+impl<'a, Message, Theme> From<VSlider<'a, Message, Theme>> for Element<'a, Message, Renderer<Theme>>
+where
+    Message: 'a + Clone,
+    Theme: 'a + StyleSheet,
+{
+    fn from(v_slider: VSlider<'a, Message, Theme>) -> Self {
+        Element::new(v_slider)
+    }
+}